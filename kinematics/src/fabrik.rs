@@ -0,0 +1,253 @@
+/* SPDX-License-Identifier: MIT
+* Copyright (c) 2024 Louis Mayencourt
+*/
+
+use glam::Vec3;
+
+use crate::kinematic_chain::{KinematicChain, Space};
+
+/// Convergence criteria of [`solve_fabrik`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FabrikSettings {
+    /// Distance between the head and the target considered as reached
+    pub tolerance: f32,
+    pub max_iterations: usize,
+}
+
+impl Default for FabrikSettings {
+    fn default() -> Self {
+        FabrikSettings {
+            tolerance: 0.5,
+            max_iterations: 10,
+        }
+    }
+}
+
+/// Outcome of [`solve_fabrik`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Reach {
+    /// The head ended within tolerance of the target
+    pub reachable: bool,
+    pub iterations: usize,
+    /// Distance left between the head and the target
+    pub error: f32,
+}
+
+/// Bring the head of an anchored chain to `target` with the FABRIK algorithm.
+///
+/// Each iteration drags the chain from the head to the target, then back from
+/// the tail to the anchor, until the head is within `settings.tolerance` of the
+/// target. A target farther than the chain length is out of reach, the chain is
+/// then stretched toward it. Chains without anchor are solved with
+/// [`crate::solve`], as the head can always reach the target.
+pub fn solve_fabrik(
+    squeleton: &mut KinematicChain,
+    target: Vec3,
+    settings: &FabrikSettings,
+) -> Reach {
+    let Some(anchor) = squeleton.anchor else {
+        let iterations = crate::solve(squeleton, target);
+        return Reach {
+            reachable: true,
+            iterations,
+            error: 0.0,
+        };
+    };
+    squeleton.target = target;
+    let space = squeleton.space;
+
+    let count = squeleton.nodes.len();
+    if count < 2 {
+        return Reach {
+            reachable: false,
+            iterations: 0,
+            error: space.project(target - anchor).length(),
+        };
+    }
+
+    if space.project(target - anchor).length() > squeleton.length() {
+        // Out of reach, stretch the chain from the anchor toward the target
+        squeleton.nodes[count - 1].0 = anchor;
+        for i in (0..count - 1).rev() {
+            let joint = squeleton.nodes[i + 1].0;
+            let node = squeleton.nodes[i].0;
+            let direction = space.project(target - joint).normalize_or_zero();
+            squeleton.nodes[i].0 =
+                node - space.project(node - joint) + direction * squeleton.segment_length(i);
+        }
+
+        return Reach {
+            reachable: false,
+            iterations: 1,
+            error: space.project(target - squeleton.nodes[0].0).length(),
+        };
+    }
+
+    let mut error = space.project(target - squeleton.nodes[0].0).length();
+    let mut iterations = 0;
+    while error > settings.tolerance && iterations < settings.max_iterations {
+        iterations += 1;
+        let pose: Vec<Vec3> = squeleton.nodes.iter().map(|node| node.0).collect();
+
+        // Forward pass, from the head at the target
+        squeleton.nodes[0].0 = target;
+        for i in 1..count {
+            let joint = squeleton.nodes[i - 1].0;
+            let mut next = place(
+                space,
+                joint,
+                squeleton.nodes[i].0,
+                squeleton.segment_length(i - 1),
+                pose[i] - pose[i - 1],
+            );
+            if i >= 2 {
+                let previous = squeleton.nodes[i - 2].0;
+                next = space.limit_bend(&squeleton.limit(i - 1), previous, joint, next);
+            }
+            squeleton.nodes[i].0 = next;
+        }
+
+        // Backward pass, from the tail at the anchor
+        squeleton.nodes[count - 1].0 = anchor;
+        for i in (0..count - 1).rev() {
+            let joint = squeleton.nodes[i + 1].0;
+            let mut next = place(
+                space,
+                joint,
+                squeleton.nodes[i].0,
+                squeleton.segment_length(i),
+                pose[i] - pose[i + 1],
+            );
+            if i + 2 < count {
+                let previous = squeleton.nodes[i + 2].0;
+                let limit = squeleton.limit(i + 1).mirrored();
+                next = space.limit_bend(&limit, previous, joint, next);
+            }
+            squeleton.nodes[i].0 = next;
+        }
+
+        error = space.project(target - squeleton.nodes[0].0).length();
+    }
+
+    Reach {
+        reachable: error <= settings.tolerance,
+        iterations,
+        error,
+    }
+}
+
+/// Place `next` `length` away from `joint` like [`Space::place`]. When both
+/// nodes meet, as when the target lies on the anchor, the segment keeps the
+/// `direction` it had before the pass instead of collapsing.
+fn place(space: Space, joint: Vec3, next: Vec3, length: f32, direction: Vec3) -> Vec3 {
+    if space.project(next - joint).length() > f32::EPSILON {
+        return space.place(joint, next, length);
+    }
+    next + space.project(direction).normalize_or_zero() * length
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::JointLimit;
+
+    const ANCHOR: Vec3 = Vec3::new(0.0, 30.0, 0.0);
+
+    /// Three links of 10, the tail pinned at its rest position
+    fn arm() -> KinematicChain {
+        KinematicChain::new(4, 10.0, Some(ANCHOR)).with_limits(vec![JointLimit::symmetric(90.0)])
+    }
+
+    #[test]
+    fn reaches_a_target_within_the_chain_length() {
+        let mut chain = arm();
+        let target = Vec3::new(12.0, 8.0, 0.0);
+
+        let reach = solve_fabrik(&mut chain, target, &FabrikSettings::default());
+
+        assert!(reach.reachable);
+        assert!(reach.error <= 0.5);
+        assert!(reach.iterations <= 10);
+        assert!(chain.nodes[0].0.distance(target) <= 0.5);
+        assert_eq!(chain.nodes[3].0, ANCHOR);
+        for nodes in chain.nodes.windows(2) {
+            assert!((nodes[0].0.distance(nodes[1].0) - 10.0).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn stretches_toward_an_unreachable_target() {
+        let mut chain = arm();
+        let target = Vec3::new(0.0, -70.0, 0.0);
+
+        let reach = solve_fabrik(&mut chain, target, &FabrikSettings::default());
+
+        assert!(!reach.reachable);
+        assert_eq!(reach.iterations, 1);
+        assert!((reach.error - 70.0).abs() < 1e-3);
+        assert!(chain.nodes[0].0.distance(Vec3::ZERO) < 1e-3);
+        assert_eq!(chain.nodes[3].0, ANCHOR);
+    }
+
+    #[test]
+    fn stops_at_the_iteration_cap() {
+        let mut chain = arm();
+        let settings = FabrikSettings {
+            tolerance: 0.0,
+            max_iterations: 2,
+        };
+
+        let reach = solve_fabrik(&mut chain, Vec3::new(15.0, 20.0, 0.0), &settings);
+
+        assert_eq!(reach.iterations, 2);
+        assert_eq!(reach.reachable, reach.error == 0.0);
+    }
+
+    #[test]
+    fn stops_once_within_tolerance() {
+        let mut chain = arm();
+        let settings = FabrikSettings {
+            tolerance: 5.0,
+            max_iterations: 10,
+        };
+
+        // The head already lies within the tolerance
+        let reach = solve_fabrik(&mut chain, Vec3::new(0.0, 3.0, 0.0), &settings);
+        assert!(reach.reachable);
+        assert_eq!(reach.iterations, 0);
+        assert_eq!(chain.nodes[0].0, Vec3::ZERO);
+
+        // A looser tolerance needs fewer iterations
+        let tight = solve_fabrik(
+            &mut arm(),
+            Vec3::new(15.0, 20.0, 0.0),
+            &FabrikSettings::default(),
+        );
+        let loose = solve_fabrik(&mut arm(), Vec3::new(15.0, 20.0, 0.0), &settings);
+        assert!(tight.reachable && loose.reachable);
+        assert!(loose.iterations <= tight.iterations);
+        assert!(loose.error <= 5.0);
+    }
+
+    #[test]
+    fn keeps_its_links_with_the_target_on_the_anchor() {
+        for limit in [
+            JointLimit::default(),
+            JointLimit::symmetric(90.0),
+            JointLimit::symmetric(180.0),
+        ] {
+            let mut chain = KinematicChain::new(4, 10.0, Some(ANCHOR)).with_limits(vec![limit]);
+
+            let reach = solve_fabrik(&mut chain, ANCHOR, &FabrikSettings::default());
+
+            assert!(reach.error.is_finite());
+            assert_eq!(reach.reachable, reach.error <= 0.5);
+            assert_eq!(chain.nodes[3].0, ANCHOR);
+            for nodes in chain.nodes.windows(2) {
+                assert!((nodes[0].0.distance(nodes[1].0) - 10.0).abs() < 1e-3);
+            }
+            crate::compute_skin(&mut chain, Vec3::ZERO);
+            assert!(chain.skin.iter().all(|point| point.is_finite()));
+        }
+    }
+}
@@ -0,0 +1,695 @@
+/* SPDX-License-Identifier: MIT
+* Copyright (c) 2024 Louis Mayencourt
+*/
+
+use std::error::Error;
+use std::fmt;
+
+use glam::{Quat, Vec2, Vec3};
+
+use crate::fabrik::{FabrikSettings, Reach};
+
+/// Bend limit of a joint, in degrees.
+///
+/// The bend is the signed angle between the segment entering the joint and the
+/// segment leaving it. Positive angles bend counter-clockwise.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct JointLimit {
+    pub min: f32,
+    pub max: f32,
+}
+
+impl JointLimit {
+    pub fn new(min: f32, max: f32) -> Self {
+        JointLimit { min, max }
+    }
+
+    /// Same limit on both side of the joint
+    pub fn symmetric(angle: f32) -> Self {
+        JointLimit::new(-angle, angle)
+    }
+
+    /// Limit bending the other way around, like the opposite leg of a creature
+    pub fn mirrored(&self) -> Self {
+        JointLimit::new(-self.max, -self.min)
+    }
+
+    pub fn clamp(&self, angle: f32) -> f32 {
+        angle.clamp(self.min, self.max)
+    }
+}
+
+impl Default for JointLimit {
+    fn default() -> Self {
+        JointLimit::symmetric(20.0)
+    }
+}
+
+/// Space in which the nodes of a chain are moved.
+///
+/// Both variants carry the axis around which the bends of the joints are
+/// measured. Positive bends turn counter-clockwise when looking down the axis.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Space {
+    /// Nodes only move in the plane perpendicular to the axis, their height
+    /// along the axis is left untouched. `Planar(Vec3::Z)` is the 2d top-down view.
+    Planar(Vec3),
+    /// Nodes move freely in 3d
+    Spatial(Vec3),
+}
+
+impl Default for Space {
+    fn default() -> Self {
+        Space::Planar(Vec3::Z)
+    }
+}
+
+impl Space {
+    /// Normalized axis of the space. A zero axis falls back to the z axis of
+    /// the top-down view, rather than spreading NaN through the chain.
+    pub fn axis(&self) -> Vec3 {
+        match self {
+            Space::Planar(axis) | Space::Spatial(axis) => axis.try_normalize().unwrap_or(Vec3::Z),
+        }
+    }
+
+    /// Part of `vector` along which the nodes are allowed to move
+    pub fn project(&self, vector: Vec3) -> Vec3 {
+        match self {
+            Space::Planar(_) => vector.reject_from_normalized(self.axis()),
+            Space::Spatial(_) => vector,
+        }
+    }
+
+    /// Place `next` exactly `length` away from `joint`, in the direction it
+    /// currently lies.
+    pub fn place(&self, joint: Vec3, next: Vec3, length: f32) -> Vec3 {
+        let direction = self.project(next - joint).normalize_or_zero();
+        // Keep the height of `next` along the axis, for planar chains
+        next - self.project(next - joint) + direction * length
+    }
+
+    /// Signed angle in degrees at `joint`, between the segment coming from
+    /// `previous` and the segment going to `next`.
+    pub fn bend_angle(&self, previous: Vec3, joint: Vec3, next: Vec3) -> f32 {
+        let segment_1 = self.project(joint - previous);
+        let segment_2 = self.project(next - joint);
+
+        let cross_product = segment_1.cross(segment_2);
+        let sign = if cross_product.dot(self.axis()) < 0.0 {
+            -1.0
+        } else {
+            1.0
+        };
+
+        (cross_product.length() * sign)
+            .atan2(segment_1.dot(segment_2))
+            .to_degrees()
+    }
+
+    /// Return the position of `next`, rotated around `joint` so the bend stays
+    /// within `limit`.
+    pub(crate) fn limit_bend(
+        &self,
+        limit: &JointLimit,
+        previous: Vec3,
+        joint: Vec3,
+        next: Vec3,
+    ) -> Vec3 {
+        let angle = self.bend_angle(previous, joint, next);
+        let clamped = limit.clamp(angle);
+        if clamped == angle {
+            return next;
+        }
+
+        let Some(direction) = self.project(joint - previous).try_normalize() else {
+            return next;
+        };
+        let segment = self.project(next - joint);
+
+        // Rotate in the plane the joint currently bends in, oriented along the axis.
+        // A straight joint bends around the axis.
+        let bend_axis = match direction.cross(segment).try_normalize() {
+            Some(bend_axis) if bend_axis.dot(self.axis()) < 0.0 => -bend_axis,
+            Some(bend_axis) => bend_axis,
+            None => self.axis(),
+        };
+        let rotated_segment =
+            Quat::from_axis_angle(bend_axis, clamped.to_radians()) * direction * segment.length();
+
+        next - segment + rotated_segment
+    }
+}
+
+/// The link lengths and the node radius of a chain don't have the same count
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProfileMismatch {
+    pub lengths: usize,
+    pub radius: usize,
+}
+
+impl fmt::Display for ProfileMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} link lengths given for {} node radius",
+            self.lengths, self.radius
+        )
+    }
+}
+
+impl Error for ProfileMismatch {}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "bevy", derive(bevy_ecs::component::Component))]
+pub struct KinematicChain {
+    pub anchor: Option<Vec3>,
+    pub target: Vec3,
+    pub nodes: Vec<(Vec3, f32)>,
+    /// Bend limit of each node, the first and last nodes are not joints
+    pub limits: Vec<JointLimit>,
+    /// Rest length of the link from each node to the next one
+    pub lengths: Vec<f32>,
+    pub space: Space,
+    /// Convergence criteria used when the chain is anchored
+    pub fabrik: FabrikSettings,
+    /// Result of the last FABRIK solve, `None` for free chains
+    pub reach: Option<Reach>,
+    pub skin: Vec<Vec2>,
+}
+
+impl KinematicChain {
+    pub fn new(count: usize, distance: f32, anchor: Option<Vec3>) -> Self {
+        Self::with_lengths(vec![distance; count], anchor)
+    }
+
+    /// One node per entry of `lengths`, each node is `lengths[i]` away from the
+    /// next one toward the tail. The last entry only sets the size of the tip.
+    pub fn with_lengths(lengths: Vec<f32>, anchor: Option<Vec3>) -> Self {
+        let radius = lengths.clone();
+        let mut chain = Self::from_profile(lengths, radius, anchor);
+        chain.target = Vec3::new(0.0, chain.length(), 0.0);
+        chain
+    }
+
+    pub fn fish_like(count: usize, distance: f32, anchor: Option<Vec3>) -> Self {
+        Self::fish_like_with_lengths(vec![distance; count], anchor)
+    }
+
+    /// Fish shaped chain, where the body size follows the mean of `lengths`
+    pub fn fish_like_with_lengths(lengths: Vec<f32>, anchor: Option<Vec3>) -> Self {
+        let count = lengths.len();
+        let size = lengths.iter().sum::<f32>() / count.max(1) as f32;
+
+        let mut radius = Vec::<f32>::new();
+        // first 1/6 nodes rises from size to create an head like shape
+        let nose = count / 6;
+        for n in 0..nose {
+            radius.push((n + count - nose) as f32 * size / 8.0);
+        }
+        // Then we have the body linearly decreasing in size
+        for n in nose..count {
+            radius.push((count - n) as f32 * size / 8.0);
+        }
+
+        let mut chain = Self::from_profile(lengths, radius, anchor);
+        chain.target = Vec3::new(200.0, 0.0, 0.0);
+        chain
+    }
+
+    /// Line up the nodes along the y axis, with the given link lengths and radius.
+    ///
+    /// Both profiles give one value per node, so they must have the same length.
+    pub fn with_profile(
+        lengths: Vec<f32>,
+        radius: Vec<f32>,
+        anchor: Option<Vec3>,
+    ) -> Result<Self, ProfileMismatch> {
+        if lengths.len() != radius.len() {
+            return Err(ProfileMismatch {
+                lengths: lengths.len(),
+                radius: radius.len(),
+            });
+        }
+        Ok(Self::from_profile(lengths, radius, anchor))
+    }
+
+    fn from_profile(lengths: Vec<f32>, radius: Vec<f32>, anchor: Option<Vec3>) -> Self {
+        let mut nodes = Vec::<(Vec3, f32)>::new();
+        let mut position = 0.0;
+        for (length, radius) in lengths.iter().zip(radius) {
+            nodes.push((Vec3::new(0.0, position, 0.0), radius));
+            position += length;
+        }
+
+        KinematicChain {
+            anchor,
+            target: Vec3::ZERO,
+            limits: vec![JointLimit::default(); nodes.len()],
+            lengths,
+            nodes,
+            space: Space::default(),
+            fabrik: FabrikSettings::default(),
+            reach: None,
+            skin: Vec::new(),
+        }
+    }
+
+    /// Rest length of the link between the node at `index` and the next one
+    pub fn segment_length(&self, index: usize) -> f32 {
+        self.lengths
+            .get(index)
+            .or(self.lengths.last())
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Index of the tail tip in `skin`. The head tip is the last point of the skin.
+    pub fn skin_tail_index(&self) -> usize {
+        // The head arc points on the left side, then one point per node
+        HEAD_ARC_POINTS + self.nodes.len()
+    }
+
+    /// Length of the chain from head to tail, when fully stretched
+    pub fn length(&self) -> f32 {
+        (1..self.nodes.len())
+            .map(|i| self.segment_length(i - 1))
+            .sum()
+    }
+
+    /// Sum of the node radius, a measure of how big the body is
+    pub fn body_size(&self) -> f32 {
+        self.nodes.iter().map(|node| node.1).sum()
+    }
+
+    /// Scale the radius of every node, the links keep their length
+    pub fn grow(&mut self, factor: f32) {
+        for node in self.nodes.iter_mut() {
+            node.1 *= factor;
+        }
+    }
+
+    /// Solve the chain in the given space, see [`Space`]
+    pub fn with_space(mut self, space: Space) -> Self {
+        self.space = space;
+        self
+    }
+
+    /// Bend limit of the node at `index`
+    pub fn limit(&self, index: usize) -> JointLimit {
+        self.limits.get(index).copied().unwrap_or_default()
+    }
+
+    /// Replace the bend limit of every node, from the head.
+    ///
+    /// Extra limits past the tail are dropped. Missing ones repeat the last
+    /// limit given, or the default one when `limits` is empty.
+    pub fn with_limits(mut self, mut limits: Vec<JointLimit>) -> Self {
+        let last = limits.last().copied().unwrap_or_default();
+        limits.resize(self.nodes.len(), last);
+        self.limits = limits;
+        self
+    }
+
+    /// Interpolate the bend limit from `head` to `tail` along the chain
+    pub fn with_limits_profile(self, head: JointLimit, tail: JointLimit) -> Self {
+        let last = self.nodes.len().saturating_sub(1).max(1) as f32;
+        let limits = (0..self.nodes.len())
+            .map(|i| {
+                let t = i as f32 / last;
+                JointLimit::new(
+                    head.min + (tail.min - head.min) * t,
+                    head.max + (tail.max - head.max) * t,
+                )
+            })
+            .collect();
+        self.with_limits(limits)
+    }
+}
+
+/// Maximum number of passes performed by [`solve`] to stabilize the chain.
+const MAX_ITERATIONS: usize = 10;
+
+/// Move the head of the chain to `target` and drag the rest of the nodes along.
+///
+/// If the chain has an anchor, the tail is pinned back to it after each pass.
+/// Returns the number of angle corrections needed to satisfy the joint limits,
+/// [`MAX_ITERATIONS`] if they are still not satisfied after the last pass.
+pub fn solve(squeleton: &mut KinematicChain, target: Vec3) -> usize {
+    squeleton.target = target;
+
+    // perform a few iteration to stabilize before drawing body
+    for i in 0..MAX_ITERATIONS {
+        if let Some(head) = squeleton.nodes.first_mut() {
+            head.0 = target;
+        }
+
+        forward_kinematics(squeleton);
+
+        if let Some(anchor) = squeleton.anchor {
+            if let Some(tail) = squeleton.nodes.last_mut() {
+                tail.0 = anchor;
+            }
+            backward_kinematics(squeleton);
+        }
+
+        if squeleton_angles_are_ok(squeleton) {
+            return i;
+        }
+        correct_angle(squeleton);
+    }
+
+    MAX_ITERATIONS
+}
+
+/// Drag every node toward its predecessor, starting from the head.
+///
+/// The bend of each joint is clamped to its limit as the chain is walked.
+pub fn forward_kinematics(squeleton: &mut KinematicChain) {
+    let space = squeleton.space;
+
+    for i in 1..squeleton.nodes.len() {
+        let node_distance = squeleton.segment_length(i - 1);
+        let head = squeleton.nodes[i - 1].0;
+        let tail = &mut squeleton.nodes[i].0;
+
+        if space.project(*tail - head).length() > node_distance {
+            *tail = space.place(head, *tail, node_distance);
+        }
+
+        if i >= 2 {
+            let previous = squeleton.nodes[i - 2].0;
+            let limit = squeleton.limit(i - 1);
+            let tail = &mut squeleton.nodes[i].0;
+            *tail = space.limit_bend(&limit, previous, head, *tail);
+        }
+    }
+}
+
+/// Drag every node toward its successor, starting from the tail.
+///
+/// The chain is walked backward, so the limits are mirrored.
+pub fn backward_kinematics(squeleton: &mut KinematicChain) {
+    let space = squeleton.space;
+
+    for i in (1..squeleton.nodes.len()).rev() {
+        let node_distance = squeleton.segment_length(i - 1);
+        let head = squeleton.nodes[i].0;
+        let tail = &mut squeleton.nodes[i - 1].0;
+
+        let offset = space.project(*tail - head);
+        if offset.try_normalize().is_none() {
+            break;
+        }
+
+        if offset.length() > node_distance {
+            *tail = space.place(head, *tail, node_distance);
+        }
+
+        if i + 1 < squeleton.nodes.len() {
+            let previous = squeleton.nodes[i + 1].0;
+            let limit = squeleton.limit(i).mirrored();
+            let tail = &mut squeleton.nodes[i - 1].0;
+            *tail = space.limit_bend(&limit, previous, head, *tail);
+        }
+    }
+}
+
+fn squeleton_angles_are_ok(squeleton: &KinematicChain) -> bool {
+    (1..squeleton.nodes.len().saturating_sub(1)).all(|i| {
+        let angle = squeleton.space.bend_angle(
+            squeleton.nodes[i - 1].0,
+            squeleton.nodes[i].0,
+            squeleton.nodes[i + 1].0,
+        );
+        // Leave some slack for the float rounding of the corrected angles
+        let limit = squeleton.limit(i);
+        angle >= limit.min - 0.01 && angle <= limit.max + 0.01
+    })
+}
+
+pub fn correct_angle(squeleton: &mut KinematicChain) {
+    let space = squeleton.space;
+
+    for i in 2..squeleton.nodes.len() {
+        let n0 = squeleton.nodes[i - 2].0;
+        let n1 = squeleton.nodes[i - 1].0;
+        let n2 = squeleton.nodes[i].0;
+
+        squeleton.nodes[i].0 = space.limit_bend(&squeleton.limit(i - 1), n0, n1, n2);
+    }
+}
+
+/// Number of skin points on each side of the head, between the tip and the first node
+const HEAD_ARC_POINTS: usize = 4;
+
+/// Compute the outline of the body around the nodes, relative to `origin`.
+///
+/// The result is stored in `squeleton.skin`, starting at the head, going down
+/// the left side, around the tail and back up the right side.
+pub fn compute_skin(squeleton: &mut KinematicChain, origin: Vec3) {
+    let mut skin_left = Vec::<Vec2>::new();
+    let mut skin_right = Vec::<Vec2>::new();
+    let mut skin_head_tail = Vec::<Vec2>::new();
+
+    let offset = match squeleton.nodes.first() {
+        Some(head) if head.0 != origin => origin.truncate(),
+        _ => Vec2::ZERO,
+    };
+
+    for (i, nodes) in squeleton.nodes.windows(2).enumerate() {
+        let (head, tail) = (nodes[0], nodes[1]);
+
+        let origin = head.0.truncate() - offset;
+        let direction = (tail.0 - head.0).truncate().normalize();
+
+        // Compute the skin points
+        let front = origin + -direction * head.1;
+        let left = origin + direction.perp() * head.1;
+        let right = origin + -direction.perp() * head.1;
+        let back = origin + direction * head.1;
+
+        if i == 0 {
+            for angle in (15..=60).step_by(15) {
+                let rotation_angle = (angle as f32).to_radians();
+                let rotation_vec = Vec2::new(rotation_angle.cos(), rotation_angle.sin());
+
+                let point = origin + (-direction.rotate(rotation_vec)).normalize() * head.1;
+                skin_right.push(point);
+            }
+
+            for angle in (-60..=-15).rev().step_by(15) {
+                let rotation_angle = (angle as f32).to_radians();
+                let rotation_vec = Vec2::new(rotation_angle.cos(), rotation_angle.sin());
+
+                let point = origin + (-direction.rotate(rotation_vec)).normalize() * head.1;
+                skin_left.push(point);
+            }
+        }
+
+        skin_left.push(left);
+        skin_right.push(right);
+        skin_head_tail.push(front);
+        skin_head_tail.push(back);
+    }
+
+    // Compute the last segment by looking at the 2 lasts nodes
+    if let [.., head, tail] = squeleton.nodes[..] {
+        let origin = tail.0.truncate() - offset;
+        let direction = (tail.0 - head.0).truncate().normalize();
+
+        // Compute the skin points
+        let left = origin + direction.perp() * tail.1;
+        let right = origin + -direction.perp() * tail.1;
+        let back = origin + direction * tail.1;
+
+        skin_left.push(left);
+        skin_right.push(right);
+        skin_head_tail.push(back);
+    }
+
+    // Combine all the skin point
+    if let Some(tail) = skin_head_tail.last() {
+        skin_right.push(*tail);
+    }
+    skin_right.reverse();
+    // push the head front at the bottom of the reversed right, to make it at the top of the vec
+    if let Some(head) = skin_head_tail.first() {
+        skin_right.push(*head);
+    }
+    skin_left.append(&mut skin_right);
+    squeleton.skin = skin_left;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn link_lengths(chain: &KinematicChain) -> Vec<f32> {
+        chain
+            .nodes
+            .windows(2)
+            .map(|nodes| nodes[0].0.distance(nodes[1].0))
+            .collect()
+    }
+
+    #[test]
+    fn solve_moves_the_head_to_the_target() {
+        let mut chain = KinematicChain::new(10, 10.0, None);
+        let target = Vec3::new(120.0, -40.0, 0.0);
+
+        solve(&mut chain, target);
+
+        assert_eq!(chain.nodes[0].0, target);
+        assert_eq!(chain.target, target);
+    }
+
+    #[test]
+    fn solve_counts_the_angle_corrections() {
+        let mut chain = KinematicChain::new(10, 10.0, None);
+
+        // Pulled straight ahead, the joints never bend
+        assert_eq!(solve(&mut chain, Vec3::new(0.0, -5.0, 0.0)), 0);
+        assert!(solve(&mut chain, Vec3::new(40.0, 60.0, 0.0)) <= MAX_ITERATIONS);
+    }
+
+    #[test]
+    fn solve_keeps_the_segment_lengths() {
+        let mut chain = KinematicChain::new(10, 10.0, None);
+
+        for target in [
+            Vec3::new(150.0, 0.0, 0.0),
+            Vec3::new(150.0, 200.0, 0.0),
+            Vec3::new(-80.0, 20.0, 0.0),
+        ] {
+            solve(&mut chain, target);
+            for length in link_lengths(&chain) {
+                assert!((length - 10.0).abs() < 1e-3, "link of length {length}");
+            }
+        }
+    }
+
+    #[test]
+    fn solve_keeps_the_tail_on_the_anchor() {
+        let anchor = Vec3::new(0.0, 30.0, 0.0);
+        let mut chain = KinematicChain::new(4, 10.0, Some(anchor));
+
+        solve(&mut chain, Vec3::new(15.0, 10.0, 0.0));
+
+        assert_eq!(chain.nodes.last().unwrap().0, anchor);
+    }
+
+    #[test]
+    fn planar_chains_keep_their_height() {
+        let mut chain = KinematicChain::new(6, 10.0, None).with_space(Space::Planar(Vec3::Z));
+        for node in chain.nodes.iter_mut() {
+            node.0.z = 5.0;
+        }
+
+        solve(&mut chain, Vec3::new(80.0, 20.0, 5.0));
+        solve(&mut chain, Vec3::new(60.0, -40.0, 5.0));
+
+        for node in &chain.nodes {
+            assert!((node.0.z - 5.0).abs() < 1e-4, "node at {}", node.0);
+        }
+        for length in link_lengths(&chain) {
+            assert!((length - 10.0).abs() < 1e-3, "link of length {length}");
+        }
+    }
+
+    #[test]
+    fn spatial_chains_move_in_3d() {
+        let mut chain = KinematicChain::new(6, 10.0, None).with_space(Space::Spatial(Vec3::Z));
+        let target = Vec3::new(40.0, 20.0, 60.0);
+
+        solve(&mut chain, target);
+
+        assert_eq!(chain.nodes[0].0, target);
+        assert!(chain.nodes[1].0.z > 0.0);
+        for length in link_lengths(&chain) {
+            assert!((length - 10.0).abs() < 1e-3, "link of length {length}");
+        }
+    }
+
+    #[test]
+    fn zero_axis_falls_back_to_the_top_down_view() {
+        for space in [Space::Planar(Vec3::ZERO), Space::Spatial(Vec3::ZERO)] {
+            assert_eq!(space.axis(), Vec3::Z);
+            assert!(space.project(Vec3::new(1.0, 2.0, 3.0)).is_finite());
+
+            let mut chain = KinematicChain::new(6, 10.0, None)
+                .with_space(space)
+                .with_limits(vec![JointLimit::symmetric(5.0)]);
+            solve(&mut chain, Vec3::new(-30.0, 10.0, 0.0));
+            assert!(chain.nodes.iter().all(|node| node.0.is_finite()));
+        }
+    }
+
+    #[test]
+    fn with_limits_pads_with_the_last_limit() {
+        let chain = KinematicChain::new(5, 10.0, None).with_limits(vec![
+            JointLimit::symmetric(5.0),
+            JointLimit::symmetric(15.0),
+        ]);
+        assert_eq!(chain.limits.len(), 5);
+        assert_eq!(chain.limit(4), JointLimit::symmetric(15.0));
+
+        let chain =
+            KinematicChain::new(2, 10.0, None).with_limits(vec![JointLimit::symmetric(5.0); 4]);
+        assert_eq!(chain.limits.len(), 2);
+
+        let chain = KinematicChain::new(3, 10.0, None).with_limits(Vec::new());
+        assert_eq!(chain.limits, vec![JointLimit::default(); 3]);
+    }
+
+    #[test]
+    fn with_profile_rejects_mismatched_profiles() {
+        let chain = KinematicChain::with_profile(vec![10.0; 4], vec![5.0; 3], None);
+        assert_eq!(
+            chain.unwrap_err(),
+            ProfileMismatch {
+                lengths: 4,
+                radius: 3
+            }
+        );
+    }
+
+    #[test]
+    fn solve_keeps_the_lengths_of_the_profile() {
+        let lengths = vec![5.0, 10.0, 20.0, 8.0];
+        let mut chain = KinematicChain::with_profile(lengths.clone(), vec![4.0; 4], None).unwrap();
+        assert_eq!(chain.nodes.len(), 4);
+
+        solve(&mut chain, Vec3::new(200.0, 50.0, 0.0));
+
+        for (length, expected) in link_lengths(&chain).into_iter().zip(lengths) {
+            assert!((length - expected).abs() < 1e-3, "link of length {length}");
+        }
+    }
+
+    #[test]
+    fn solve_clamps_the_bent_joints() {
+        let limit = JointLimit::new(-5.0, 40.0);
+        let mut chain = KinematicChain::new(8, 10.0, None).with_limits(vec![limit]);
+
+        // Fold the chain back on itself, both ways
+        for target in [
+            Vec3::new(30.0, 60.0, 0.0),
+            Vec3::new(-30.0, 0.0, 0.0),
+            Vec3::new(30.0, -10.0, 0.0),
+        ] {
+            solve(&mut chain, target);
+            for i in 1..chain.nodes.len() - 1 {
+                let angle = chain.space.bend_angle(
+                    chain.nodes[i - 1].0,
+                    chain.nodes[i].0,
+                    chain.nodes[i + 1].0,
+                );
+                assert!(
+                    angle >= limit.min - 0.01 && angle <= limit.max + 0.01,
+                    "joint {i} bent by {angle}"
+                );
+            }
+        }
+    }
+}
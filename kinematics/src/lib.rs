@@ -0,0 +1,19 @@
+/* SPDX-License-Identifier: MIT
+* Copyright (c) 2024 Louis Mayencourt
+*/
+
+//! Kinematic chain math used to animate the creatures.
+//!
+//! This crate doesn't depend on any game engine, so the chains can be solved
+//! headless (tools, servers, tests). Enable the `bevy` feature to use
+//! [`KinematicChain`] directly as a bevy `Component`.
+
+mod fabrik;
+mod kinematic_chain;
+mod locomotion;
+pub mod outline;
+
+pub use fabrik::*;
+pub use glam::{Vec2, Vec3};
+pub use kinematic_chain::*;
+pub use locomotion::*;
@@ -0,0 +1,162 @@
+/* SPDX-License-Identifier: MIT
+* Copyright (c) 2024 Louis Mayencourt
+*/
+
+use glam::{Quat, Vec3};
+
+use crate::kinematic_chain::Space;
+
+/// Physical limits of the head of a chain, moving it toward its target over time.
+///
+/// Without locomotion the head jumps onto the target. With it, the head
+/// accelerates, turns and brakes within the limits, so the body never snaps
+/// when the target jumps.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "bevy", derive(bevy_ecs::component::Component))]
+pub struct Locomotion {
+    /// Maximum speed, in units per second
+    pub max_speed: f32,
+    /// Maximum change of speed, in units per second squared
+    pub max_acceleration: f32,
+    /// Maximum change of heading, in degrees per second
+    pub max_turn_rate: f32,
+    pub velocity: Vec3,
+}
+
+impl Default for Locomotion {
+    fn default() -> Self {
+        Locomotion::new(300.0, 800.0, 360.0)
+    }
+}
+
+impl Locomotion {
+    pub fn new(max_speed: f32, max_acceleration: f32, max_turn_rate: f32) -> Self {
+        Locomotion {
+            max_speed,
+            max_acceleration,
+            max_turn_rate,
+            velocity: Vec3::ZERO,
+        }
+    }
+
+    /// Move `head` toward `target` for `delta` seconds, and return its new position
+    pub fn step(&mut self, head: Vec3, target: Vec3, space: Space, delta: f32) -> Vec3 {
+        let offset = space.project(target - head);
+        let distance = offset.length();
+        if delta <= 0.0 {
+            return head;
+        }
+
+        // Fastest speed from which the head can still brake on the target
+        let braking_speed = (2.0 * self.max_acceleration * distance).sqrt();
+        let desired_speed = self.max_speed.min(braking_speed);
+        let desired = offset.normalize_or_zero() * desired_speed;
+
+        // Turn toward the target, then change speed
+        let speed = self.velocity.length();
+        let max_turn = self.max_turn_rate.to_radians() * delta;
+        let direction = match (self.velocity.try_normalize(), desired.try_normalize()) {
+            (Some(from), Some(to)) => {
+                let angle = from.angle_between(to);
+                if angle <= max_turn {
+                    to
+                } else {
+                    let axis = from.cross(to).try_normalize().unwrap_or(space.axis());
+                    Quat::from_axis_angle(axis, max_turn) * from
+                }
+            }
+            (None, Some(to)) => to,
+            (Some(from), None) => from,
+            (None, None) => Vec3::ZERO,
+        };
+        let max_change = self.max_acceleration * delta;
+        let speed = speed + (desired_speed - speed).clamp(-max_change, max_change);
+        self.velocity = space.project(direction * speed);
+
+        let movement = self.velocity * delta;
+        if movement.length() >= distance {
+            // Don't overshoot a target reached on this frame
+            return head + offset;
+        }
+        head + movement
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DELTA: f32 = 1.0 / 60.0;
+
+    #[test]
+    fn brakes_to_rest_on_the_target() {
+        let mut locomotion = Locomotion::default();
+        let target = Vec3::new(100.0, 0.0, 0.0);
+        let mut head = Vec3::ZERO;
+
+        for _ in 0..120 {
+            head = locomotion.step(head, target, Space::default(), DELTA);
+            assert!(head.x <= target.x, "overshot to {head}");
+        }
+
+        assert_eq!(head, target);
+        assert!(locomotion.velocity.length() < 1e-3);
+    }
+
+    #[test]
+    fn caps_the_turn_rate() {
+        let mut locomotion = Locomotion::new(100.0, 800.0, 90.0);
+        locomotion.velocity = Vec3::new(100.0, 0.0, 0.0);
+
+        // The target is a quarter turn away, only 9 degrees are allowed
+        locomotion.step(
+            Vec3::ZERO,
+            Vec3::new(0.0, 1000.0, 0.0),
+            Space::default(),
+            0.1,
+        );
+
+        let turn = Vec3::X.angle_between(locomotion.velocity).to_degrees();
+        assert!((turn - 9.0).abs() < 1e-3, "turned by {turn} degrees");
+    }
+
+    #[test]
+    fn speeds_up_to_max_speed() {
+        let mut locomotion = Locomotion::new(300.0, 800.0, 360.0);
+        let target = Vec3::new(10000.0, 0.0, 0.0);
+        let mut head = Vec3::ZERO;
+
+        let mut speed = 0.0;
+        for _ in 0..60 {
+            head = locomotion.step(head, target, Space::default(), DELTA);
+            let next = locomotion.velocity.length();
+            assert!(next - speed <= 800.0 * DELTA + 1e-3);
+            assert!(next <= 300.0 + 1e-3);
+            speed = next;
+        }
+
+        assert!((speed - 300.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn planar_space_keeps_the_height() {
+        let mut locomotion = Locomotion::default();
+        let mut head = Vec3::new(0.0, 0.0, 5.0);
+
+        for _ in 0..10 {
+            head = locomotion.step(head, Vec3::new(50.0, 50.0, 20.0), Space::default(), DELTA);
+            assert_eq!(head.z, 5.0);
+            assert_eq!(locomotion.velocity.z, 0.0);
+        }
+
+        // A spatial chain climbs toward the target
+        let mut head = Vec3::new(0.0, 0.0, 5.0);
+        head = locomotion.step(
+            head,
+            Vec3::new(50.0, 50.0, 20.0),
+            Space::Spatial(Vec3::Z),
+            DELTA,
+        );
+        assert!(head.z > 5.0);
+    }
+}
@@ -0,0 +1,195 @@
+/* SPDX-License-Identifier: MIT
+* Copyright (c) 2024 Louis Mayencourt
+*/
+
+//! Smooth closed curves through the skin points of a chain.
+//!
+//! The curves are cardinal splines, like the bevy `CubicCardinalSpline`: the
+//! tangent at each point is `tension` times the vector between its neighbours.
+//! A tension of 0.5 gives a Catmull-Rom spline, 0.0 gives back the polygon.
+
+use glam::Vec2;
+
+/// Cubic bezier segment, starting where the previous one ended
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BezierSegment {
+    pub control_1: Vec2,
+    pub control_2: Vec2,
+    pub end: Vec2,
+}
+
+fn tangents(points: &[Vec2], tension: f32) -> Vec<Vec2> {
+    let count = points.len();
+    (0..count)
+        .map(|i| (points[(i + 1) % count] - points[(i + count - 1) % count]) * tension)
+        .collect()
+}
+
+/// Closed curve through `points`, as cubic bezier segments starting from `points[0]`
+pub fn bezier_outline(points: &[Vec2], tension: f32) -> Vec<BezierSegment> {
+    let count = points.len();
+    if count < 3 {
+        return Vec::new();
+    }
+
+    let tangents = tangents(points, tension);
+    (0..count)
+        .map(|i| {
+            let next = (i + 1) % count;
+            BezierSegment {
+                control_1: points[i] + tangents[i] / 3.0,
+                control_2: points[next] - tangents[next] / 3.0,
+                end: points[next],
+            }
+        })
+        .collect()
+}
+
+/// Closed curve through `points`, sampled `samples` times between each point
+pub fn smooth_outline(points: &[Vec2], tension: f32, samples: usize) -> Vec<Vec2> {
+    let Some(&start) = points.first() else {
+        return Vec::new();
+    };
+    let samples = samples.max(1);
+
+    let mut outline = Vec::with_capacity(points.len() * samples);
+    let mut previous = start;
+    for segment in bezier_outline(points, tension) {
+        for step in 0..samples {
+            let t = step as f32 / samples as f32;
+            let u = 1.0 - t;
+            outline.push(
+                previous * u * u * u
+                    + segment.control_1 * 3.0 * u * u * t
+                    + segment.control_2 * 3.0 * u * t * t
+                    + segment.end * t * t * t,
+            );
+        }
+        previous = segment.end;
+    }
+
+    outline
+}
+
+/// The `count - 2` triangles filling a closed outline of `count` points, as indices.
+///
+/// The outline is split in two sides going from `head` to `tail`, one walking
+/// the points forward and the other backward. The space between both sides is
+/// filled like a strip, which suits the elongated body of the creatures.
+pub fn triangulate(count: usize, head: usize, tail: usize) -> Vec<u32> {
+    if count < 3 || head >= count || tail >= count || head == tail {
+        return Vec::new();
+    }
+
+    let left_len = (tail + count - head) % count;
+    let right_len = (head + count - tail) % count;
+    let left = |i: usize| ((head + i) % count) as u32;
+    let right = |j: usize| ((head + count - j) % count) as u32;
+
+    let mut indices = Vec::with_capacity((count - 2) * 3);
+    let (mut i, mut j) = (0, 0);
+    // Both sides start on the head and end on the tail, so the first and the
+    // last steps would give flat triangles: skip the first, stop before the last
+    while i + j + 1 < count {
+        // Advance the side which is the least far along, to keep the triangles even
+        let advance_left = j == right_len
+            || (i < left_len
+                && (i + 1) as f32 / left_len as f32 <= (j + 1) as f32 / right_len as f32);
+
+        let triangle = if advance_left {
+            i += 1;
+            [left(i - 1), right(j), left(i)]
+        } else {
+            j += 1;
+            [left(i), right(j - 1), right(j)]
+        };
+        if i + j > 1 {
+            indices.extend(triangle);
+        }
+    }
+
+    indices
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Points around a circle, counter-clockwise
+    fn circle(count: usize) -> Vec<Vec2> {
+        (0..count)
+            .map(|i| Vec2::from_angle(i as f32 / count as f32 * std::f32::consts::TAU) * 10.0)
+            .collect()
+    }
+
+    fn signed_area(a: Vec2, b: Vec2, c: Vec2) -> f32 {
+        (b - a).perp_dot(c - a) / 2.0
+    }
+
+    #[test]
+    fn triangulate_fills_the_outline() {
+        let points = circle(12);
+        for (head, tail) in [(0, 6), (0, 1), (3, 10), (11, 4)] {
+            let indices = triangulate(points.len(), head, tail);
+
+            assert_eq!(indices.len(), (points.len() - 2) * 3);
+            assert!(indices.iter().all(|index| (*index as usize) < points.len()));
+
+            // Every triangle has the same winding, and together they cover the polygon
+            let areas: Vec<f32> = indices
+                .chunks(3)
+                .map(|t| {
+                    signed_area(
+                        points[t[0] as usize],
+                        points[t[1] as usize],
+                        points[t[2] as usize],
+                    )
+                })
+                .collect();
+            assert!(
+                areas.iter().all(|area| *area < 0.0),
+                "{head} to {tail}: {areas:?}"
+            );
+            let polygon: f32 = (1..points.len() - 1)
+                .map(|i| signed_area(points[0], points[i], points[i + 1]))
+                .sum();
+            let covered: f32 = areas.iter().map(|area| -area).sum();
+            assert!((covered - polygon).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn triangulate_rejects_degenerate_outlines() {
+        assert!(triangulate(2, 0, 1).is_empty());
+        assert!(triangulate(6, 2, 2).is_empty());
+        assert!(triangulate(6, 0, 6).is_empty());
+        assert_eq!(triangulate(3, 0, 1).len(), 3);
+    }
+
+    #[test]
+    fn bezier_outline_goes_through_the_points() {
+        let points = circle(5);
+        let segments = bezier_outline(&points, 0.5);
+
+        assert_eq!(segments.len(), points.len());
+        for (i, segment) in segments.iter().enumerate() {
+            assert_eq!(segment.end, points[(i + 1) % points.len()]);
+        }
+        assert!(bezier_outline(&points[..2], 0.5).is_empty());
+    }
+
+    #[test]
+    fn smooth_outline_goes_through_the_points() {
+        let points = circle(7);
+        let outline = smooth_outline(&points, 0.5, 4);
+
+        assert_eq!(outline.len(), points.len() * 4);
+        for (i, point) in points.iter().enumerate() {
+            assert!(outline[i * 4].distance(*point) < 1e-4);
+        }
+        // Without tension the samples stay on the polygon
+        let polygon = smooth_outline(&points, 0.0, 2);
+        let middle = (points[0] + points[1]) / 2.0;
+        assert!(polygon[1].distance(middle) < 1e-4);
+    }
+}
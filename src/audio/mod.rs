@@ -19,6 +19,5 @@ fn setup_sound(asset_server: Res<AssetServer>, mut commands: Commands) {
             mode: bevy::audio::PlaybackMode::Loop,
             ..default()
         },
-        ..default()
     });
 }
\ No newline at end of file
@@ -3,7 +3,8 @@
 */
 
 use bevy::prelude::*;
-use rand::seq::SliceRandom;
+use rand::{seq::SliceRandom, Rng};
+use serde::Deserialize;
 
 pub const COLOR_BLUE: Color = Color::srgb(132.0 / 255.0, 166.0 / 255.0, 199.0 / 255.0);
 pub const COLOR_LIGHT_BLUE: Color = Color::srgb(175.0 / 255.0, 188.0 / 255.0, 198.0 / 255.0);
@@ -12,7 +13,7 @@ pub const COLOR_RED: Color = Color::srgb(159.0 / 255.0, 75.0 / 255.0, 63.0 / 255
 pub const COLOR_WHITE: Color = Color::srgb(233.0 / 255.0, 228.0 / 255.0, 217.0 / 255.0);
 pub const COLOR_BLACK: Color = Color::srgb(58.0 / 255.0, 59.0 / 255.0, 59.0 / 255.0);
 
-pub fn random() -> Color {
+pub fn random(rng: &mut impl Rng) -> Color {
     let val = [
     COLOR_BLUE,
     COLOR_LIGHT_BLUE,
@@ -20,7 +21,33 @@ pub fn random() -> Color {
     COLOR_RED,
     COLOR_WHITE,
     COLOR_BLACK
-    ].choose(&mut rand::thread_rng());
+    ].choose(rng);
 
     *val.unwrap()
-}
\ No newline at end of file
+}
+
+/// Color of the palette, as written in the asset files
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub enum PaletteColor {
+    Blue,
+    LightBlue,
+    Green,
+    Red,
+    White,
+    Black,
+    Srgb(u8, u8, u8),
+}
+
+impl From<PaletteColor> for Color {
+    fn from(color: PaletteColor) -> Self {
+        match color {
+            PaletteColor::Blue => COLOR_BLUE,
+            PaletteColor::LightBlue => COLOR_LIGHT_BLUE,
+            PaletteColor::Green => COLOR_GREEN,
+            PaletteColor::Red => COLOR_RED,
+            PaletteColor::White => COLOR_WHITE,
+            PaletteColor::Black => COLOR_BLACK,
+            PaletteColor::Srgb(r, g, b) => Color::srgb_u8(r, g, b),
+        }
+    }
+}
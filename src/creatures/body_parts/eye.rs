@@ -2,56 +2,182 @@
 * Copyright (c) 2024 Louis Mayencourt
 */
 
-use bevy::prelude::*;
+use bevy::{
+    prelude::*,
+    sprite::{MaterialMesh2dBundle, Mesh2dHandle},
+};
+use rand::prelude::*;
+use serde::Deserialize;
 
-use crate::corbusier_colors::*;
 use crate::creatures::body_parts::*;
+use crate::creatures::Creature;
+use crate::simulation_rng::EntityRng;
+use crate::MyWorldCoords;
 
-/// Fix point eye
+/// Distance from which a creature notices the cursor or its neighbours
+const LOOK_RANGE: f32 = 250.0;
+
+/// Sclera of an eye, holding its pupil
+#[derive(Component)]
+pub struct Eye {
+    /// Half width and half height of the sclera
+    pub size: Vec2,
+}
+
+/// Pupil moving within the sclera of its parent eye
 #[derive(Component)]
-pub struct Eye;
+pub struct Pupil {
+    pub radius: f32,
+}
+
+/// What the eyes of a creature are looking at
+#[allow(dead_code)]
+#[derive(Component, Clone, Copy, Debug, Default, Deserialize)]
+pub enum LookAt {
+    /// Straight in front of the creature
+    #[default]
+    Ahead,
+    /// The mouse cursor, when it comes close
+    Cursor,
+    /// The closest creature around
+    Nearest,
+    /// Another creature, or any entity with a position
+    #[serde(skip_deserializing)]
+    Entity(Entity),
+    #[serde(skip_deserializing)]
+    Point(Vec3),
+}
+
+/// Eyes of a creature closing from time to time
+#[derive(Component)]
+pub struct Blink {
+    /// Mean time between two blinks, in seconds
+    pub interval: f32,
+    /// Time the eyes take to close and open again, in seconds
+    pub duration: f32,
+    /// Time left before the next blink, negative while blinking.
+    /// Not scheduled until the creature gets its random stream
+    timer: Option<f32>,
+}
+
+impl Default for Blink {
+    fn default() -> Self {
+        Blink {
+            interval: 4.0,
+            duration: 0.2,
+            timer: None,
+        }
+    }
+}
+
+impl Blink {
+    /// How open the eyes are, from 0 when closed to 1
+    fn openness(&self) -> f32 {
+        let timer = match self.timer {
+            Some(timer) if timer <= 0.0 => timer,
+            _ => return 1.0,
+        };
+        let progress = -timer / self.duration.max(f32::EPSILON);
+        1.0 - (progress.min(1.0) * std::f32::consts::PI).sin() * 0.9
+    }
+}
+
+/// Spawn an eye with its pupil, sized like an ellipse of half size `size`
+pub fn spawn_eye(
+    parent: &mut ChildBuilder,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<ColorMaterial>,
+    anchor: BodyPartAnchor,
+    size: Vec2,
+    sclera: Color,
+    pupil: Color,
+) {
+    let radius = size.min_element() * 0.6;
+    parent
+        .spawn((
+            Eye { size },
+            anchor,
+            MaterialMesh2dBundle {
+                mesh: Mesh2dHandle(meshes.add(Ellipse::new(size.x, size.y))),
+                material: materials.add(sclera),
+                ..default()
+            },
+        ))
+        .with_children(|eye| {
+            eye.spawn((
+                Pupil { radius },
+                MaterialMesh2dBundle {
+                    mesh: Mesh2dHandle(meshes.add(Circle::new(radius))),
+                    material: materials.add(pupil),
+                    transform: Transform::from_xyz(0.0, 0.0, 0.1),
+                    ..default()
+                },
+            ));
+        });
+}
 
+pub fn blink(time: Res<Time>, mut blinks: Query<(&mut Blink, &mut EntityRng)>) {
+    for (mut blink, mut rng) in blinks.iter_mut() {
+        let interval = blink.interval;
+        let duration = blink.duration;
+        let timer = blink.timer.get_or_insert_with(|| interval * rng.gen_range(0.0..1.0));
+        *timer -= time.delta_seconds();
+        if *timer < -duration {
+            *timer = interval * rng.gen_range(0.5..1.5);
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
 pub fn draw_eye(
-    mut gizmos: Gizmos,
-    mut q_squeleton: Query<(&KinematicChain, &mut Children)>,
-    mut q_eye: Query<(&BodyPartAnchor, &mut Transform), With<Eye>>,
+    cursor: Res<MyWorldCoords>,
+    q_squeleton: Query<(Entity, &RenderedChain, &Children, Option<&LookAt>, Option<&Blink>)>,
+    q_creatures: Query<(Entity, &RenderedChain), With<Creature>>,
+    q_positions: Query<(Option<&RenderedChain>, &GlobalTransform)>,
+    mut q_eye: Query<(&Eye, &BodyPartAnchor, &mut Transform, Option<&Children>)>,
+    mut q_pupils: Query<(&Pupil, &mut Transform), Without<Eye>>,
 ) {
-    for (squeleton, children) in q_squeleton.iter_mut() {
+    for (entity, squeleton, children, look_at, blink) in q_squeleton.iter() {
+        let Some(head) = squeleton.nodes.first() else {
+            continue;
+        };
+        let head = head.0;
+
+        let target = match look_at.copied().unwrap_or_default() {
+            LookAt::Ahead => None,
+            LookAt::Cursor => Some(cursor.0.extend(head.z)).filter(|cursor| cursor.distance(head) < LOOK_RANGE),
+            LookAt::Nearest => q_creatures
+                .iter()
+                .filter(|(other, _)| *other != entity)
+                .filter_map(|(_, other)| other.nodes.first().map(|node| node.0))
+                .filter(|other| other.distance(head) < LOOK_RANGE)
+                .min_by(|a, b| a.distance(head).total_cmp(&b.distance(head))),
+            LookAt::Entity(other) => q_positions.get(other).ok().map(|(chain, transform)| {
+                chain
+                    .and_then(|chain| chain.nodes.first())
+                    .map_or(transform.translation(), |node| node.0)
+            }),
+            LookAt::Point(point) => Some(point),
+        };
+        let openness = blink.map_or(1.0, Blink::openness);
+
         for &child in children.iter() {
-            if let Ok((eye, mut transform)) = q_eye.get_mut(child) {
-                let anchor_node = squeleton.nodes[eye.anchor];
-                let anchor_head = squeleton.nodes[eye.anchor - 1];
-
-                let distance = anchor_head.0.distance(anchor_node.0);
-                let ray = Ray2d {
-                    origin: anchor_head.0.truncate(),
-                    direction: Dir2::new_unchecked(
-                        (anchor_node.0 - anchor_head.0).truncate().normalize(),
-                    ),
-                };
-
-                let left = ray.origin + ray.direction.perp() * anchor_node.1 * 0.75;
-                let right = ray.origin + -ray.direction.perp() * anchor_node.1 * 0.75;
-
-                match eye.position {
-                    BodyPartPosition::Dorsal => {
-                        transform.translation = anchor_node.0;
-                        transform.translation.z = 1.0;
-                        let angle = ray.direction.to_angle();
-                        transform.rotation =
-                            Quat::from_rotation_z(angle + std::f32::consts::FRAC_PI_2);
-                    }
-                    BodyPartPosition::Left => {
-                        transform.translation = left.extend(1.0);
-                        let angle = ray.direction.to_angle();
-                        transform.rotation =
-                            Quat::from_rotation_z(angle + std::f32::consts::FRAC_PI_2 + 0.2);
-                    }
-                    BodyPartPosition::Right => {
-                        transform.translation = right.extend(1.0);
-                        let angle = ray.direction.to_angle();
-                        transform.rotation =
-                            Quat::from_rotation_z(angle + std::f32::consts::FRAC_PI_2 - 0.2);
+            if let Ok((eye, anchor, mut transform, pupils)) = q_eye.get_mut(child) {
+                let attachment = get_attachment_position(squeleton, anchor);
+                transform.translation = attachment.translation;
+                transform.rotation = attachment.rotation;
+                transform.scale = Vec3::new(1.0, openness, 1.0);
+
+                // Without target, the pupil rests in the middle of the eye
+                let direction = target.map_or(Vec3::ZERO, |target| {
+                    (target - transform.translation).truncate().normalize_or_zero().extend(0.0)
+                });
+                let local = (transform.rotation.inverse() * direction).truncate();
+
+                for &pupil_entity in pupils.into_iter().flatten() {
+                    if let Ok((pupil, mut pupil_transform)) = q_pupils.get_mut(pupil_entity) {
+                        let room = (eye.size - pupil.radius).max(Vec2::ZERO);
+                        pupil_transform.translation = (local * room).extend(0.1);
                     }
                 }
             }
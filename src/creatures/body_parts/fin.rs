@@ -4,56 +4,72 @@
 
 use bevy::prelude::*;
 
-use crate::corbusier_colors::*;
 use crate::creatures::body_parts::*;
 
 /// Fix point Fin
 #[derive(Component, Default)]
 pub struct Fin;
 
+/// Fin beating back and forth, more as the creature goes faster
+#[derive(Component)]
+pub struct Flap {
+    /// Amplitude of the beat at full speed, in degrees
+    pub amplitude: f32,
+    /// Speed from which the fin beats at full amplitude
+    pub full_speed: f32,
+    /// Beats per second
+    pub frequency: f32,
+    phase: f32,
+    /// Speed of the anchor, smoothed over a few frames
+    speed: f32,
+    last_position: Option<Vec3>,
+}
+
+impl Flap {
+    pub fn new(amplitude: f32) -> Self {
+        Flap {
+            amplitude,
+            full_speed: 150.0,
+            frequency: 2.0,
+            phase: 0.0,
+            speed: 0.0,
+            last_position: None,
+        }
+    }
+}
+
 pub fn draw_fin(
-    mut gizmos: Gizmos,
-    mut q_squeleton: Query<(&KinematicChain, &mut Children)>,
-    mut q_fins: Query<(&BodyPartAnchor, &mut Transform), With<Fin>>,
+    time: Res<Time>,
+    mut q_squeleton: Query<(&RenderedChain, &mut Children)>,
+    mut q_fins: Query<(&BodyPartAnchor, &mut Transform, Option<&mut Flap>), With<Fin>>,
 ) {
+    let delta = time.delta_seconds();
+
     for (squeleton, children) in q_squeleton.iter_mut() {
         for &child in children.iter() {
-            if let Ok((fin, mut transform)) = q_fins.get_mut(child) {
-                let anchor_node = squeleton.nodes[fin.anchor];
-                let anchor_head = squeleton.nodes[fin.anchor - 1];
-
-                let distance = anchor_head.0.distance(anchor_node.0);
-                let ray = Ray2d {
-                    origin: anchor_head.0.truncate(),
-                    direction: Dir2::new_unchecked(
-                        (anchor_node.0 - anchor_head.0).truncate().normalize(),
-                    ),
-                };
-
-                let left = ray.origin + ray.direction.perp() * anchor_node.1;
-                let right = ray.origin + -ray.direction.perp() * anchor_node.1;
-
-                match fin.position {
-                    BodyPartPosition::Dorsal => {
-                        transform.translation = anchor_node.0;
-                        transform.translation.z = 1.0;
-                        let angle = ray.direction.to_angle();
-                        transform.rotation =
-                            Quat::from_rotation_z(angle - std::f32::consts::FRAC_PI_2);
-                    }
-                    BodyPartPosition::Left => {
-                        transform.translation = left.extend(-1.0);
-                        let angle = ray.direction.to_angle();
-                        transform.rotation =
-                            Quat::from_rotation_z(angle - std::f32::consts::PI / 5.0);
-                    }
-                    BodyPartPosition::Right => {
-                        transform.translation = right.extend(-1.0);
-                        let angle = ray.direction.to_angle();
-                        transform.rotation =
-                            Quat::from_rotation_z(angle + std::f32::consts::PI / 5.0);
+            if let Ok((fin, mut transform, flap)) = q_fins.get_mut(child) {
+                let mut part = *fin;
+
+                if let Some(mut flap) = flap {
+                    let position = squeleton.nodes[fin.anchor].0;
+                    if let Some(last) = flap.last_position.replace(position) {
+                        if delta > 0.0 {
+                            let speed = last.distance(position) / delta;
+                            let ease = 1.0 - (-5.0 * delta).exp();
+                            flap.speed += (speed - flap.speed) * ease;
+                        }
                     }
+
+                    let frequency = flap.frequency;
+                    flap.phase = (flap.phase + delta * frequency * std::f32::consts::TAU)
+                        % std::f32::consts::TAU;
+                    let amplitude = flap.amplitude * (flap.speed / flap.full_speed).min(1.0);
+                    part.rotation += amplitude * flap.phase.sin();
                 }
+
+                let attachment = get_attachment_position(squeleton, &part);
+                transform.translation = attachment.translation;
+                transform.rotation = attachment.rotation;
             }
         }
     }
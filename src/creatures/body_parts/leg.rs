@@ -3,67 +3,300 @@
 */
 
 use bevy::prelude::*;
+use serde::Deserialize;
 
-use crate::corbusier_colors::*;
 use crate::creatures::body_parts::*;
 use crate::creatures::Creature;
 
 /// Moving leg following body position
+#[derive(Component, Default)]
+pub struct Leg {
+    /// Step in progress, the foot is lifted until it ends
+    pub step: Option<Step>,
+}
+
+/// Foot moving from one planted position to the next
+#[derive(Clone, Copy)]
+pub struct Step {
+    pub from: Vec3,
+    pub to: Vec3,
+    /// Fraction of the step done, from 0 to 1
+    pub progress: f32,
+}
+
+/// Order in which the legs of a creature are lifted
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+pub enum GaitPattern {
+    /// Diagonal pairs of legs step together
+    #[default]
+    Trot,
+    /// One leg at a time, back to front on the left side then on the right side
+    Walk,
+    /// Alternating tripods, like insects: the front and back legs of one side
+    /// step with the middle leg of the other side. Longer bodies are split in
+    /// blocks of three pairs, stepping one after the other.
+    Tripod,
+    /// Waves of steps going from the tail to the head, for many legged creatures
+    Wave,
+}
+
+/// Legs of the wave pattern between two legs stepping together
+const WAVE_GROUPS: usize = 3;
+
+/// Pairs of legs in a block of the tripod pattern
+const TRIPOD_PAIRS: usize = 3;
+
+impl GaitPattern {
+    /// Number of groups stepping in turn, for `pairs` of legs
+    pub fn groups(&self, pairs: usize) -> usize {
+        match self {
+            GaitPattern::Trot => 2,
+            GaitPattern::Tripod => 2 * pairs.div_ceil(TRIPOD_PAIRS),
+            GaitPattern::Walk => 2 * pairs,
+            GaitPattern::Wave => WAVE_GROUPS,
+        }
+        .max(1)
+    }
+
+    /// Group of the leg on `side` (0 for left, 1 for right) of the pair `pair`,
+    /// counted from the head
+    pub fn group(&self, pair: usize, side: usize, pairs: usize) -> usize {
+        let from_tail = pairs.saturating_sub(pair + 1);
+        match self {
+            GaitPattern::Trot => (pair + side) % 2,
+            GaitPattern::Tripod => {
+                // The middle leg of the block is on the other side of the front and back ones
+                let middle = usize::from(pair % TRIPOD_PAIRS == 1);
+                2 * (pair / TRIPOD_PAIRS) + (middle + side) % 2
+            }
+            GaitPattern::Walk => side * pairs + from_tail,
+            GaitPattern::Wave => (from_tail + side) % WAVE_GROUPS,
+        }
+    }
+
+    fn next(&self) -> GaitPattern {
+        match self {
+            GaitPattern::Trot => GaitPattern::Walk,
+            GaitPattern::Walk => GaitPattern::Tripod,
+            GaitPattern::Tripod => GaitPattern::Wave,
+            GaitPattern::Wave => GaitPattern::Trot,
+        }
+    }
+}
+
+/// Coordinate the legs of a creature, so they step group after group.
+///
+/// A group only lifts its feet when all the other legs are planted, then
+/// each foot follows an arc over `step_duration` seconds. Creatures without
+/// gait move each leg on its own.
 #[derive(Component)]
-pub struct Leg;
+pub struct Gait {
+    pub pattern: GaitPattern,
+    /// Time to move a foot to its next position, in seconds
+    pub step_duration: f32,
+    /// Distance the foot swings away from the body in the middle of the step
+    pub lift_height: f32,
+    /// Group allowed to step next
+    pub active_group: usize,
+}
+
+impl Default for Gait {
+    fn default() -> Self {
+        Gait::new(GaitPattern::Trot)
+    }
+}
+
+impl Gait {
+    pub fn new(pattern: GaitPattern) -> Self {
+        Gait {
+            pattern,
+            step_duration: 0.15,
+            lift_height: 6.0,
+            active_group: 0,
+        }
+    }
+}
+
+/// Cycle through the gait patterns of all the creatures when pressing G
+pub fn switch_gait_pattern(mut gaits: Query<&mut Gait>, keyboard: Res<ButtonInput<KeyCode>>) {
+    if keyboard.just_pressed(KeyCode::KeyG) {
+        for mut gait in gaits.iter_mut() {
+            gait.pattern = gait.pattern.next();
+            gait.active_group = 0;
+        }
+    }
+}
+
+/// Leg of a creature, as seen by the gait
+struct LegState {
+    entity: Entity,
+    anchor: usize,
+    side: usize,
+    needs_step: bool,
+    /// Where the foot should be planted
+    top_position: Vec3,
+    planted: bool,
+}
 
-pub fn clear_leg_rotation(mut q_legs: Query<&mut Transform, With<Leg>>) {
-    for mut leg in q_legs.iter_mut() {
-        leg.rotation = Quat::from_rotation_x(0.);
+/// Lift the foot of the leg, toward its top position
+fn start_step(
+    q_legs: &mut Query<(&mut Leg, &BodyPartAnchor, &mut KinematicChain), Without<Creature>>,
+    state: &LegState,
+) {
+    if let Ok((mut leg, _, chain)) = q_legs.get_mut(state.entity) {
+        leg.step = Some(Step {
+            from: chain.target,
+            to: state.top_position,
+            progress: 0.0,
+        });
     }
 }
 
+#[allow(clippy::type_complexity)]
 pub fn draw_leg(
-    mut gizmos: Gizmos,
-    mut q_squeleton: Query<(&KinematicChain, &mut Children), With<Creature>>,
-    mut q_legs: Query<(&mut BodyPartAnchor, &mut KinematicChain, &mut Transform), (Without<Creature>, With<Leg>)>,
+    time: Res<Time>,
+    mut q_squeleton: Query<(&KinematicChain, &Children, Option<&mut Gait>), With<Creature>>,
+    mut q_legs: Query<(&mut Leg, &BodyPartAnchor, &mut KinematicChain), Without<Creature>>,
 ) {
-    for (squeleton, children) in q_squeleton.iter_mut() {
-        for &child in children.iter() {
-            if let Ok((leg, mut chain, mut transform)) = q_legs.get_mut(child) {
-                let anchor_node = squeleton.nodes[leg.anchor];
-                let anchor_head = squeleton.nodes[leg.anchor - 1];
+    for (squeleton, children, mut gait) in q_squeleton.iter_mut() {
+        let default_gait = Gait::default();
+        let (step_duration, lift_height) = match gait.as_deref() {
+            Some(gait) => (gait.step_duration, gait.lift_height),
+            None => (default_gait.step_duration, default_gait.lift_height),
+        };
+        let mut legs = Vec::new();
 
-                *transform = get_attachment_position(anchor_node, anchor_head, &leg.position);
+        for &child in children.iter() {
+            if let Ok((mut leg, anchor, mut chain)) = q_legs.get_mut(child) {
+                let anchor_node = squeleton.nodes[anchor.anchor];
+                let anchor_head = squeleton.nodes[anchor.anchor - 1];
 
-                gizmos.circle_2d(transform.translation.truncate(), 5.0, COLOR_GREEN);
+                // The leg is drawn around its anchor, once the chain is interpolated
+                chain.anchor = Some(get_attachment_position(squeleton, anchor).translation);
 
                 // calculate the position of the foot
-                chain.anchor = Some(transform.translation);
-
-                let leg_length = chain.distance * chain.nodes.len() as f32;
-                let foot_direction = get_perpendicular_body_ray(anchor_node, anchor_head);
-                let middle_position;
-                match leg.position {
-                    BodyPartPosition::Left => {
-                        middle_position = anchor_node.0.truncate()
-                            - foot_direction.direction * leg_length / std::f32::consts::SQRT_2
-                    }
-                    BodyPartPosition::Right => {
-                        middle_position = anchor_node.0.truncate()
-                            + foot_direction.direction * leg_length / std::f32::consts::SQRT_2
-                    }
-                    BodyPartPosition::Dorsal => middle_position = anchor_node.0.truncate(),
-                }
 
-                let top_position = middle_position
-                    - foot_direction.direction.perp() * leg_length / std::f32::consts::SQRT_2;
-                let bottom_position = middle_position
-                    + foot_direction.direction.perp() * leg_length / std::f32::consts::SQRT_2;
+                let leg_length = chain.length();
+                let front = get_radial_direction(anchor_node, anchor_head, 0.0);
+                let outward = match anchor.position {
+                    BodyPartPosition::Dorsal => Vec2::ZERO,
+                    position => get_radial_direction(anchor_node, anchor_head, position.angle()),
+                };
+                let middle_position =
+                    anchor_node.0.truncate() + outward * leg_length / std::f32::consts::SQRT_2;
+                let side = usize::from(!anchor.position.is_left());
+
+                let top_position =
+                    (middle_position + front * leg_length / std::f32::consts::SQRT_2).extend(0.0);
                 // gizmos.circle_2d(middle_position, 5.0 , COLOR_GREEN);
                 // gizmos.circle_2d(top_position, 5.0 , COLOR_RED);
-                // gizmos.circle_2d(bottom_position, 5.0 , COLOR_RED);
 
-                let distance = chain.target.distance(anchor_head.0);
-                if distance > leg_length {
-                    chain.target = top_position.extend(0.0);
+                // Move the lifted foot along its arc, toward where the body is now
+                if let Some(mut step) = leg.step {
+                    step.progress += time.delta_seconds() / step_duration.max(f32::EPSILON);
+                    step.to = top_position;
+                    if step.progress >= 1.0 {
+                        chain.target = step.to;
+                        leg.step = None;
+                    } else {
+                        let lift = (step.progress * std::f32::consts::PI).sin() * lift_height;
+                        chain.target =
+                            step.from.lerp(step.to, step.progress) + outward.extend(0.0) * lift;
+                        leg.step = Some(step);
+                    }
                 }
+
+                // Step when the foot is left behind, or can't be reached anymore
+                let distance = chain.target.distance(anchor_head.0);
+                let out_of_reach = chain.reach.is_some_and(|reach| !reach.reachable);
+                legs.push(LegState {
+                    entity: child,
+                    anchor: anchor.anchor,
+                    side,
+                    needs_step: leg.step.is_none() && (distance > leg_length || out_of_reach),
+                    top_position,
+                    planted: leg.step.is_none(),
+                });
+            }
+        }
+
+        let Some(gait) = gait.as_deref_mut() else {
+            for leg in legs.iter().filter(|leg| leg.needs_step) {
+                start_step(&mut q_legs, leg);
             }
+            continue;
+        };
+
+        // A new group only lifts once the feet of its partners are planted again
+        if !legs.iter().all(|leg| leg.planted) {
+            continue;
         }
+
+        // Pairs of legs are numbered from the head
+        let mut anchors: Vec<usize> = legs.iter().map(|leg| leg.anchor).collect();
+        anchors.sort_unstable();
+        anchors.dedup();
+        let pairs = anchors.len();
+        let groups = gait.pattern.groups(pairs);
+        let group_of = |leg: &LegState| {
+            let pair = anchors.iter().position(|anchor| *anchor == leg.anchor).unwrap_or(0);
+            gait.pattern.group(pair, leg.side, pairs)
+        };
+
+        let active = gait.active_group % groups;
+        let group_steps = legs
+            .iter()
+            .any(|leg| leg.needs_step && group_of(leg) == active);
+        let any_step = legs.iter().any(|leg| leg.needs_step);
+
+        if group_steps {
+            // The whole group steps together, even the feet not left behind yet
+            for leg in legs.iter().filter(|leg| group_of(leg) == active) {
+                start_step(&mut q_legs, leg);
+            }
+        }
+        if any_step {
+            gait.active_group = (active + 1) % groups;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Legs of each group, as (pair, side)
+    fn groups(pattern: GaitPattern, pairs: usize) -> Vec<Vec<(usize, usize)>> {
+        let mut groups = vec![Vec::new(); pattern.groups(pairs)];
+        for pair in 0..pairs {
+            for side in 0..2 {
+                groups[pattern.group(pair, side, pairs)].push((pair, side));
+            }
+        }
+        groups
+    }
+
+    #[test]
+    fn tripods_alternate_on_six_legs() {
+        assert_eq!(
+            groups(GaitPattern::Tripod, 3),
+            [vec![(0, 0), (1, 1), (2, 0)], vec![(0, 1), (1, 0), (2, 1)]]
+        );
+    }
+
+    #[test]
+    fn tripods_step_block_after_block() {
+        let tripods = groups(GaitPattern::Tripod, 6);
+        assert_eq!(tripods.len(), 4);
+        assert_eq!(tripods[2], [(3, 0), (4, 1), (5, 0)]);
+        assert_ne!(tripods.len(), groups(GaitPattern::Trot, 6).len());
+    }
+
+    #[test]
+    fn trot_steps_with_diagonal_pairs() {
+        assert_eq!(
+            groups(GaitPattern::Trot, 2),
+            [vec![(0, 0), (1, 1)], vec![(0, 1), (1, 0)]]
+        );
     }
 }
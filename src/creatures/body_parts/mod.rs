@@ -3,88 +3,125 @@
 */
 
 use bevy::prelude::*;
+use serde::Deserialize;
 
+use crate::creatures::interpolation::RenderedChain;
 use crate::creatures::kinematic_chain::KinematicChain;
 
 pub mod eye;
 pub mod fin;
 pub mod leg;
+pub mod tail_fin;
+pub mod tentacle;
+pub mod tongue;
 
-#[derive(Default)]
+#[derive(Default, Clone, Copy, Debug, Deserialize)]
 pub enum BodyPartPosition {
     #[default]
     Dorsal,
     Left,
     Right,
+    /// Around the node, at `angle` degrees counter-clockwise from the front
+    Radial { angle: f32 },
 }
 
-#[derive(Component, Default)]
+impl BodyPartPosition {
+    /// Angle around the node in degrees, counter-clockwise from the front
+    pub fn angle(&self) -> f32 {
+        match self {
+            BodyPartPosition::Dorsal => 0.0,
+            BodyPartPosition::Right => 90.0,
+            BodyPartPosition::Left => 270.0,
+            BodyPartPosition::Radial { angle } => *angle,
+        }
+    }
+
+    /// Position on the left half of the body
+    pub fn is_left(&self) -> bool {
+        self.angle().rem_euclid(360.0) > 180.0
+    }
+}
+
+#[derive(Component, Clone, Copy, Debug)]
 pub struct BodyPartAnchor {
     pub anchor: usize,
     pub position: BodyPartPosition,
+    /// Distance from the node, as a fraction of its radius. Dorsal parts stay on the node.
+    pub radius: f32,
+    /// Rotation in degrees, positive sweeps the part toward the tail on both sides
+    pub rotation: f32,
+    /// Depth of the part, positive is drawn over the skin
+    pub layer: f32,
 }
 
-fn get_attachment_position(
-    node: (Vec3, f32),
-    head: (Vec3, f32),
-    part_type: &BodyPartPosition,
-) -> Transform {
-    // let distance = head.0.distance(node.0);
-    let ray = Ray2d {
-        origin: head.0.truncate(),
-        direction: Dir2::new_unchecked((node.0 - head.0).truncate().normalize()),
-    };
+impl Default for BodyPartAnchor {
+    fn default() -> Self {
+        BodyPartAnchor {
+            anchor: 1,
+            position: BodyPartPosition::default(),
+            radius: 1.0,
+            rotation: 0.0,
+            layer: 0.0,
+        }
+    }
+}
 
-    // let ray = get_body_direction(node, head);
+impl BodyPartAnchor {
+    pub fn new(anchor: usize, position: BodyPartPosition) -> Self {
+        BodyPartAnchor {
+            anchor,
+            position,
+            ..default()
+        }
+    }
 
-    let left = ray.origin + ray.direction.perp() * node.1;
-    let right = ray.origin + -ray.direction.perp() * node.1;
+    pub fn with_radius(mut self, radius: f32) -> Self {
+        self.radius = radius;
+        self
+    }
 
-    let mut transform = Transform::default();
-    match part_type {
-        BodyPartPosition::Dorsal => {
-            transform.translation = node.0;
-            transform.translation.z = 1.0;
-            let angle = ray.direction.to_angle();
-            transform.rotation = Quat::from_rotation_z(angle - std::f32::consts::FRAC_PI_2);
-        }
-        BodyPartPosition::Left => {
-            transform.translation = left.extend(-1.0);
-            let angle = ray.direction.to_angle();
-            transform.rotation = Quat::from_rotation_z(angle - std::f32::consts::PI / 2.0);
-        }
-        BodyPartPosition::Right => {
-            transform.translation = right.extend(-1.0);
-            let angle = ray.direction.to_angle();
-            transform.rotation = Quat::from_rotation_z(angle + std::f32::consts::PI / 2.0);
-        }
+    pub fn with_rotation(mut self, rotation: f32) -> Self {
+        self.rotation = rotation;
+        self
     }
 
-    return transform;
+    pub fn with_layer(mut self, layer: f32) -> Self {
+        self.layer = layer;
+        self
+    }
 }
 
-fn get_body_direction(node: (Vec3, f32), head: (Vec3, f32)) -> Ray2d {
-    let ray = Ray2d {
-        origin: head.0.truncate(),
-        direction: Dir2::new_unchecked((node.0 - head.0).truncate().normalize()),
-    };
+/// Place a body part around its anchor node, facing away from the spine.
+///
+/// The part is oriented with the node before its anchor, so the anchor can't be
+/// the head node.
+fn get_attachment_position(squeleton: &KinematicChain, part: &BodyPartAnchor) -> Transform {
+    let node = squeleton.nodes[part.anchor];
+    let head = squeleton.nodes[part.anchor - 1];
 
-    // Left side
-    return Ray2d {
-        origin: ray.origin + ray.direction.perp() * node.1,
-        direction: Dir2::new_unchecked(ray.direction.perp()),
+    let outward = get_radial_direction(node, head, part.position.angle());
+    let distance = match part.position {
+        BodyPartPosition::Dorsal => 0.0,
+        _ => node.1 * part.radius,
     };
-}
-
-fn get_perpendicular_body_ray(node: (Vec3, f32), head: (Vec3, f32)) -> Ray2d {
-    let ray = Ray2d {
-        origin: head.0.truncate(),
-        direction: Dir2::new_unchecked((node.0 - head.0).truncate().normalize()),
+    // Mirror the rotation on the left, so both sides sweep the same way
+    let rotation = if part.position.is_left() {
+        -part.rotation
+    } else {
+        part.rotation
     };
 
-    // Left side
-    return Ray2d {
-        origin: ray.origin + ray.direction.perp() * node.1,
-        direction: Dir2::new_unchecked(-ray.direction.perp()),
-    };
+    Transform {
+        translation: (node.0.truncate() + outward * distance).extend(part.layer),
+        rotation: Quat::from_rotation_z(
+            outward.to_angle() - std::f32::consts::FRAC_PI_2 + rotation.to_radians(),
+        ),
+        ..default()
+    }
+}
+
+/// Direction at `angle` degrees counter-clockwise from the front of the body
+fn get_radial_direction(node: (Vec3, f32), head: (Vec3, f32), angle: f32) -> Vec2 {
+    let front = (head.0 - node.0).truncate().normalize();
+    Vec2::from_angle(angle.to_radians()).rotate(front)
 }
@@ -0,0 +1,110 @@
+/* SPDX-License-Identifier: MIT
+* Copyright (c) 2024 Louis Mayencourt
+*/
+
+use bevy::{
+    prelude::*,
+    render::{
+        mesh::{Indices, PrimitiveTopology, VertexAttributeValues},
+        render_asset::RenderAssetUsages,
+    },
+    sprite::Mesh2dHandle,
+};
+
+use crate::creatures::body_parts::*;
+
+/// Joints before the anchor used to measure how much the tail is bent
+const CURVATURE_JOINTS: usize = 3;
+
+/// Caudal fin, fanning out behind the anchor node and bending with the tail.
+///
+/// The fin is made of two lobes. They spread apart and lean toward the bend as
+/// the end of the spine curves.
+#[derive(Component)]
+pub struct TailFin {
+    pub length: f32,
+    /// Half angle between the lobes of the straight fin, in degrees
+    pub spread: f32,
+    /// Extra spread for each degree of curvature
+    pub fan: f32,
+    /// Lean of the fin for each degree of curvature
+    pub skew: f32,
+}
+
+impl TailFin {
+    pub fn new(length: f32, spread: f32) -> Self {
+        TailFin {
+            length,
+            spread,
+            fan: 0.3,
+            skew: 0.8,
+        }
+    }
+
+    /// Outline of the fin, from its root at the origin toward `direction`
+    fn points(&self, direction: Vec2, curvature: f32) -> Vec<[f32; 3]> {
+        let spread = (self.spread + curvature.abs() * self.fan).to_radians();
+        let skew = (curvature * self.skew).to_radians();
+        let along = |angle: f32, length: f32| {
+            let point = Vec2::from_angle(angle).rotate(direction) * length;
+            [point.x, point.y, 0.0]
+        };
+
+        vec![
+            [0.0, 0.0, 0.0],
+            // The lobes bend progressively, half way at their middle
+            along(skew / 2.0 + spread / 2.0, self.length / 2.0),
+            along(skew + spread, self.length),
+            along(skew, self.length * 0.6),
+            along(skew - spread, self.length),
+            along(skew / 2.0 - spread / 2.0, self.length / 2.0),
+        ]
+    }
+
+    /// Mesh of the straight fin, pointing away from the head, updated by [`draw_tail_fin`]
+    pub fn mesh(&self) -> Mesh {
+        Mesh::new(
+            PrimitiveTopology::TriangleList,
+            RenderAssetUsages::RENDER_WORLD | RenderAssetUsages::MAIN_WORLD,
+        )
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, self.points(Vec2::NEG_Y, 0.0))
+        .with_inserted_indices(Indices::U32(vec![0, 1, 3, 1, 2, 3, 0, 3, 5, 5, 3, 4]))
+    }
+}
+
+pub fn draw_tail_fin(
+    mut meshes: ResMut<Assets<Mesh>>,
+    q_squeleton: Query<(&RenderedChain, &Children)>,
+    mut q_fins: Query<(&TailFin, &BodyPartAnchor, &Mesh2dHandle, &mut Transform)>,
+) {
+    for (squeleton, children) in q_squeleton.iter() {
+        for &child in children.iter() {
+            if let Ok((fin, anchor, handle, mut transform)) = q_fins.get_mut(child) {
+                // Sum of the bend of the last joints, signed like the spine limits
+                let first = anchor.anchor.saturating_sub(CURVATURE_JOINTS).max(1);
+                let curvature: f32 = (first..anchor.anchor)
+                    .map(|i| {
+                        squeleton.space.bend_angle(
+                            squeleton.nodes[i - 1].0,
+                            squeleton.nodes[i].0,
+                            squeleton.nodes[i + 1].0,
+                        )
+                    })
+                    .sum();
+
+                // The fin faces the front of the body like the other parts,
+                // and fans out behind its attachment
+                *transform = get_attachment_position(squeleton, anchor);
+
+                let Some(mesh) = meshes.get_mut(&handle.0) else {
+                    continue;
+                };
+                if let Some(VertexAttributeValues::Float32x3(positions)) =
+                    mesh.attribute_mut(Mesh::ATTRIBUTE_POSITION)
+                {
+                    *positions = fin.points(Vec2::NEG_Y, curvature);
+                }
+            }
+        }
+    }
+}
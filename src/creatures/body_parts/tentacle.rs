@@ -0,0 +1,97 @@
+/* SPDX-License-Identifier: MIT
+* Copyright (c) 2024 Louis Mayencourt
+*/
+
+use bevy::prelude::*;
+
+use crate::creatures::body_parts::*;
+use crate::creatures::Creature;
+
+/// Speed of the body above which the tentacles trail behind it
+const MOVING_SPEED: f32 = 20.0;
+
+/// Chain anchored to the body, trailing behind it when it moves and grabbing
+/// toward the creatures passing by when it rests
+#[derive(Component)]
+pub struct Tentacle {
+    /// How much the tentacle is pulled back by the movement of the body
+    pub drag: f32,
+    /// How fast the tip follows its rest position, per second
+    pub stiffness: f32,
+    /// Phase of the curling motion, to not move all the tentacles together
+    pub phase: f32,
+    last_anchor: Option<Vec3>,
+}
+
+impl Tentacle {
+    pub fn new(phase: f32) -> Self {
+        Tentacle {
+            drag: 2.0,
+            stiffness: 4.0,
+            phase,
+            last_anchor: None,
+        }
+    }
+}
+
+pub fn move_tentacles(
+    time: Res<Time>,
+    q_squeleton: Query<(Entity, &KinematicChain, &Children), With<Creature>>,
+    mut q_tentacles: Query<(&mut Tentacle, &BodyPartAnchor, &mut KinematicChain), Without<Creature>>,
+) {
+    let delta = time.delta_seconds();
+    if delta <= 0.0 {
+        return;
+    }
+
+    for (entity, squeleton, children) in q_squeleton.iter() {
+        for &child in children.iter() {
+            if let Ok((mut tentacle, anchor, mut chain)) = q_tentacles.get_mut(child) {
+                // The skin is drawn around the anchor, the tentacle must not turn with the body
+                let attachment = get_attachment_position(squeleton, anchor);
+                chain.anchor = Some(attachment.translation);
+
+                let base = attachment.translation;
+                let velocity = tentacle
+                    .last_anchor
+                    .map_or(Vec3::ZERO, |last| (base - last) / delta);
+                tentacle.last_anchor = Some(base);
+
+                let length = chain.length();
+                let outward = (attachment.rotation * Vec3::Y).truncate();
+                let speed = velocity.truncate().length();
+
+                let rest = if speed > MOVING_SPEED {
+                    // Trail behind the body, more as it goes faster
+                    let pull = (speed / 100.0).min(1.0) * tentacle.drag;
+                    let direction =
+                        (outward - velocity.truncate().normalize() * pull).normalize_or_zero();
+                    let curl = (time.elapsed_seconds() * 3.0 + tentacle.phase).sin() * 0.3;
+                    base.truncate()
+                        + direction * length * 0.9
+                        + direction.perp() * curl * length
+                } else {
+                    // Grab toward the closest creature in reach
+                    let prey = q_squeleton
+                        .iter()
+                        .filter(|(other, ..)| *other != entity)
+                        .filter_map(|(_, other, _)| other.nodes.first())
+                        .map(|node| node.0.truncate())
+                        .filter(|head| head.distance(base.truncate()) < length)
+                        .min_by(|a, b| {
+                            a.distance(base.truncate()).total_cmp(&b.distance(base.truncate()))
+                        });
+
+                    prey.unwrap_or_else(|| {
+                        let curl = (time.elapsed_seconds() + tentacle.phase).sin() * 0.5;
+                        base.truncate() + Vec2::from_angle(curl).rotate(outward) * length * 0.7
+                    })
+                };
+
+                let ease = 1.0 - (-tentacle.stiffness * delta).exp();
+                let target = chain.target.truncate().lerp(rest, ease);
+                chain.target = target.extend(chain.target.z);
+            }
+        }
+    }
+}
@@ -0,0 +1,77 @@
+/* SPDX-License-Identifier: MIT
+* Copyright (c) 2024 Louis Mayencourt
+*/
+
+use bevy::{
+    prelude::*,
+    render::{
+        mesh::{Indices, PrimitiveTopology},
+        render_asset::RenderAssetUsages,
+    },
+};
+
+use crate::creatures::body_parts::*;
+
+/// Forked tongue, flicking out of the mouth from time to time
+#[derive(Component)]
+pub struct Tongue {
+    /// Time between two flicks, in seconds
+    pub period: f32,
+    /// Time the tongue stays out, in seconds
+    pub duration: f32,
+    /// Offset of the flicks in the period, in seconds, so the creatures don't
+    /// all flick together
+    pub phase: f32,
+}
+
+impl Tongue {
+    pub fn new(phase: f32) -> Self {
+        Tongue {
+            period: 2.0,
+            duration: 0.4,
+            phase,
+        }
+    }
+}
+
+/// Forked tongue shape, from its root at the origin to its tips at `length` on the y axis
+pub fn tongue_mesh(length: f32, width: f32) -> Mesh {
+    let fork = length * 0.7;
+    let positions = vec![
+        [-width / 2.0, 0.0, 0.0],
+        [width / 2.0, 0.0, 0.0],
+        [width / 2.0, fork, 0.0],
+        [-width / 2.0, fork, 0.0],
+        [0.0, fork, 0.0],
+        [-width * 1.5, length, 0.0],
+        [width * 1.5, length, 0.0],
+    ];
+
+    Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::default())
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+        .with_inserted_indices(Indices::U32(vec![0, 1, 2, 0, 2, 3, 3, 4, 5, 4, 2, 6]))
+}
+
+pub fn draw_tongue(
+    time: Res<Time>,
+    mut q_squeleton: Query<(&RenderedChain, &mut Children)>,
+    mut q_tongue: Query<(&Tongue, &BodyPartAnchor, &mut Transform, &mut Visibility)>,
+) {
+    for (squeleton, children) in q_squeleton.iter_mut() {
+        for &child in children.iter() {
+            if let Ok((tongue, anchor, mut transform, mut visibility)) = q_tongue.get_mut(child) {
+                // The tongue goes out and back in during the first part of each period
+                let flick = (time.elapsed_seconds() + tongue.phase) % tongue.period.max(f32::EPSILON);
+                if flick > tongue.duration {
+                    *visibility = Visibility::Hidden;
+                    continue;
+                }
+                *visibility = Visibility::Inherited;
+                let extension = (flick / tongue.duration * std::f32::consts::PI).sin();
+
+                *transform = get_attachment_position(squeleton, anchor)
+                    .with_scale(Vec3::new(1.0, extension.max(0.01), 1.0));
+            }
+        }
+    }
+}
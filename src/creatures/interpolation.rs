@@ -0,0 +1,172 @@
+/* SPDX-License-Identifier: MIT
+* Copyright (c) 2024 Louis Mayencourt
+*/
+
+use bevy::prelude::*;
+
+use crate::creatures::kinematic_chain::KinematicChain;
+
+/// Rate of the simulation steps, in steps per second
+pub const SIMULATION_HZ: f64 = 60.0;
+
+/// Positions of a chain at the last two simulation steps.
+///
+/// The simulation runs at a fixed rate in `FixedUpdate`. Between two steps
+/// the chain is drawn at the position in between matching the time left
+/// over, from its [`RenderedChain`].
+#[derive(Component, Default)]
+pub struct Interpolated {
+    previous: Vec<Vec3>,
+    current: Vec<Vec3>,
+    previous_anchor: Option<Vec3>,
+    current_anchor: Option<Vec3>,
+}
+
+/// Chain as it is drawn, in between its last two simulation steps.
+///
+/// The renderers read it instead of the [`KinematicChain`], which only holds
+/// the simulated positions.
+#[derive(Component, Deref)]
+pub struct RenderedChain(KinematicChain);
+
+impl RenderedChain {
+    pub fn new(chain: KinematicChain) -> Self {
+        RenderedChain(chain)
+    }
+}
+
+/// Interpolate the rendering of every new chain
+pub fn add_interpolation(
+    mut commands: Commands,
+    chains: Query<(Entity, &KinematicChain), Without<Interpolated>>,
+) {
+    for (entity, chain) in chains.iter() {
+        commands
+            .entity(entity)
+            .insert((Interpolated::default(), RenderedChain::new(chain.clone())));
+    }
+}
+
+/// Keep the simulated positions before a simulation step
+pub fn store_previous(mut chains: Query<(&KinematicChain, &mut Interpolated)>) {
+    for (chain, mut interpolated) in chains.iter_mut() {
+        interpolated.previous = chain.nodes.iter().map(|node| node.0).collect();
+        interpolated.previous_anchor = chain.anchor;
+    }
+}
+
+/// Keep the simulated positions after a simulation step
+pub fn store_current(mut chains: Query<(&KinematicChain, &mut Interpolated)>) {
+    for (chain, mut interpolated) in chains.iter_mut() {
+        interpolated.current = chain.nodes.iter().map(|node| node.0).collect();
+        interpolated.current_anchor = chain.anchor;
+    }
+}
+
+/// Move the rendered chains in between their last two simulation steps, and
+/// rebuild their skin there, before they are drawn
+pub fn interpolate_chains(
+    fixed_time: Res<Time<Fixed>>,
+    mut chains: Query<(&KinematicChain, &Interpolated, &mut RenderedChain, &mut Transform)>,
+) {
+    let alpha = fixed_time.overstep_fraction();
+    for (chain, interpolated, mut rendered, mut transform) in chains.iter_mut() {
+        let count = chain.nodes.len();
+        if interpolated.previous.len() != count || interpolated.current.len() != count {
+            continue;
+        }
+
+        let rendered = &mut rendered.0;
+        rendered.clone_from(chain);
+        let steps = interpolated.previous.iter().zip(&interpolated.current);
+        for (node, (previous, current)) in rendered.nodes.iter_mut().zip(steps) {
+            node.0 = previous.lerp(*current, alpha);
+        }
+
+        // Anchored chains are drawn around their anchor, the others are drawn
+        // relative to their entity, at the origin of the world
+        if let (Some(previous), Some(current)) =
+            (interpolated.previous_anchor, interpolated.current_anchor)
+        {
+            let anchor = previous.lerp(current, alpha);
+            rendered.anchor = Some(anchor);
+            transform.translation = anchor;
+        }
+        kinematics::compute_skin(rendered, transform.translation);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use rand::RngCore;
+
+    use super::*;
+    use crate::simulation_rng::EntityRng;
+    use crate::test_support::{aquarium, run_steps, spawn_wanderer, Steps, STEP};
+
+    /// Lizard and octopus wandering around, updated every `frame`
+    fn wanderers(frame: Duration) -> App {
+        let mut app = aquarium(7, frame);
+        spawn_wanderer(&mut app, include_str!("../../assets/species/lizard.species.ron"));
+        spawn_wanderer(&mut app, include_str!("../../assets/species/octopus.species.ron"));
+        app
+    }
+
+    /// Simulated positions of every chain and next number of every creature
+    /// stream, after `steps` simulation steps
+    fn simulate(frame: Duration, steps: usize) -> (Vec<Vec<Vec3>>, Vec<u64>) {
+        let mut app = wanderers(frame);
+        run_steps(&mut app, steps);
+        assert_eq!(app.world().resource::<Steps>().0, steps);
+
+        let world = app.world_mut();
+        let mut chains: Vec<(Entity, Vec<Vec3>)> = world
+            .query::<(Entity, &KinematicChain)>()
+            .iter(world)
+            .map(|(entity, chain)| (entity, chain.nodes.iter().map(|node| node.0).collect()))
+            .collect();
+        chains.sort_by_key(|(entity, _)| *entity);
+        let mut streams: Vec<(Entity, u64)> = world
+            .query::<(Entity, &mut EntityRng)>()
+            .iter_mut(world)
+            .map(|(entity, mut rng)| (entity, rng.next_u64()))
+            .collect();
+        streams.sort_by_key(|(entity, _)| *entity);
+
+        (
+            chains.into_iter().map(|(_, nodes)| nodes).collect(),
+            streams.into_iter().map(|(_, next)| next).collect(),
+        )
+    }
+
+    #[test]
+    fn simulation_ignores_the_frame_rate() {
+        // Two steps per frame, or a step every few frames ending in between two steps
+        let slow = simulate(STEP * 2, 60);
+        let fast = simulate(Duration::from_millis(7), 60);
+
+        // Both bodies, the four legs and the eight tentacles
+        assert_eq!(slow.0.len(), 14);
+        assert_eq!(slow, fast);
+    }
+
+    #[test]
+    fn rendering_leaves_the_simulation_untouched() {
+        let mut app = wanderers(Duration::from_millis(7));
+        run_steps(&mut app, 10);
+
+        let world = app.world_mut();
+        let mut chains = world.query::<(&KinematicChain, &Interpolated, &RenderedChain)>();
+        let mut interpolated = 0;
+        for (chain, positions, rendered) in chains.iter(world) {
+            let simulated: Vec<Vec3> = chain.nodes.iter().map(|node| node.0).collect();
+            assert_eq!(simulated, positions.current);
+            if rendered.nodes.iter().zip(&simulated).any(|(node, position)| node.0 != *position) {
+                interpolated += 1;
+            }
+        }
+        assert!(interpolated > 0);
+    }
+}
@@ -2,162 +2,84 @@
 * Copyright (c) 2024 Louis Mayencourt
 */
 
-use bevy::{
-    prelude::*,
-    sprite::{MaterialMesh2dBundle, Mesh2dHandle},
-};
-use bevy_prototype_lyon::prelude::*;
-
-use crate::corbusier_colors::*;
+use bevy::prelude::*;
+use serde::Deserialize;
 
-mod body_parts;
+pub mod body_parts;
+pub mod interpolation;
 pub mod kinematic_chain;
+pub mod skin_mesh;
 pub mod species;
+pub mod undulation;
 
-use crate::creatures::kinematic_chain::{reach_target, KinematicChain};
-use body_parts::leg::Leg;
-use body_parts::*;
+use crate::creatures::interpolation::{
+    add_interpolation, interpolate_chains, store_current, store_previous, SIMULATION_HZ,
+};
+use crate::creatures::kinematic_chain::{draw_squeleton, reach_target};
 
 pub struct CreaturesPlugin;
 
 impl Plugin for CreaturesPlugin {
     fn build(&self, app: &mut App) {
-        // app.add_systems(Startup, setup);
-        app.add_systems(Update, reach_target);
-        app.add_systems(Update, draw_body);
-        app.add_systems(Update, body_parts::fin::draw_fin);
-        app.add_systems(Update, body_parts::eye::draw_eye);
-        app.add_systems(Update, body_parts::leg::draw_leg);
-        app.add_systems(Update, body_parts::leg::clear_leg_rotation);
+        app.init_asset::<species::definition::SpeciesDefinition>();
+        app.init_asset_loader::<species::definition::SpeciesDefinitionLoader>();
+        app.insert_resource(Time::<Fixed>::from_hz(SIMULATION_HZ));
+        app.add_systems(Update, species::definition::build_species);
+
+        // Simulation, at a fixed rate
+        app.configure_sets(
+            FixedUpdate,
+            (SimulationSet::Target, SimulationSet::Animation, SimulationSet::Solve).chain(),
+        );
+        app.add_systems(FixedFirst, (add_interpolation, store_previous).chain());
+        app.add_systems(FixedUpdate, reach_target.in_set(SimulationSet::Solve));
+        app.add_systems(FixedUpdate, (
+            undulation::undulate,
+            body_parts::tentacle::move_tentacles,
+            body_parts::leg::draw_leg,
+            body_parts::eye::blink,
+        ).in_set(SimulationSet::Animation));
+        app.add_systems(FixedLast, store_current);
+
+        // Rendering, in between the last two simulation steps
+        app.add_systems(Update, interpolate_chains);
+        app.add_systems(Update, (
+            draw_squeleton,
+            skin_mesh::update_skin_mesh,
+            body_parts::fin::draw_fin,
+            body_parts::tail_fin::draw_tail_fin,
+            body_parts::eye::draw_eye,
+            body_parts::tongue::draw_tongue,
+        ).after(interpolate_chains));
+        app.add_systems(Update, skin_mesh::spawn_skin_mesh);
+        app.add_systems(Update, body_parts::leg::switch_gait_pattern);
     }
 }
 
+/// Steps of a simulation update, running one after the other in `FixedUpdate`
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum SimulationSet {
+    /// Choose where the creatures are heading
+    Target,
+    /// Move the head target and the body parts around it
+    Animation,
+    /// Move the chains to their target
+    Solve,
+}
+
 #[derive(Component)]
 pub struct Creature;
 
 #[derive(Component)]
 pub struct Playable;
 
-
-#[derive(Component)]
-struct Skin;
-
-fn setup(
-    mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
-) {
-    let points = [
-        Vec2::new(-1.0, -0.3),
-        Vec2::new(1.5, 0.0),
-        Vec2::new(0.0, 1.0),
-    ]
-    .map(|x| x * 100.);
-
-    let shape = shapes::RoundedPolygon {
-        points: points.into_iter().collect(),
-        radius: 10.,
-        closed: false,
-    };
-
-    let fish = species::fish::Fish::new(10, COLOR_BLUE);
-    let playable = fish.spawn(&mut commands, &mut meshes, &mut materials);
-
-    commands.entity(playable).insert((Creature, Playable));
-
-    commands.entity(playable).with_children(|parent| {
-            // Spawn 4 legs
-            parent.spawn((
-                Leg,
-                BodyPartAnchor {
-                    anchor: 3,
-                    position: BodyPartPosition::Right,
-                },
-                KinematicChain::new(3, 20.0, None),
-                ShapeBundle {
-                    path: GeometryBuilder::build_as(&shape),
-                    ..default()
-                },
-                Fill::color(COLOR_GREEN),
-            ));
-            parent.spawn((
-                Leg,
-                BodyPartAnchor {
-                    anchor: 3,
-                    position: BodyPartPosition::Left,
-                },
-                KinematicChain::new(3, 20.0, None),
-                ShapeBundle {
-                    path: GeometryBuilder::build_as(&shape),
-                    ..default()
-                },
-                Fill::color(COLOR_GREEN),
-            ));
-            parent.spawn((
-                Leg,
-                BodyPartAnchor {
-                    anchor: 13,
-                    position: BodyPartPosition::Right,
-                },
-                KinematicChain::new(3, 20.0, None),
-                ShapeBundle {
-                    path: GeometryBuilder::build_as(&shape),
-                    ..default()
-                },
-                Fill::color(COLOR_GREEN),
-            ));
-            parent.spawn((
-                Leg,
-                BodyPartAnchor {
-                    anchor: 13,
-                    position: BodyPartPosition::Left,
-                },
-                KinematicChain::new(3, 20.0, None),
-                ShapeBundle {
-                    path: GeometryBuilder::build_as(&shape),
-                    ..default()
-                },
-                Fill::color(COLOR_GREEN),
-            ));
-    });
-}
-
-fn draw_body(
-    mut gizmos: Gizmos,
-    mut squeleton: Query<(&mut KinematicChain, &mut Path)>,
-    mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
-    skins: Query<Entity, With<Skin>>,
-) {
-    // Clear all the preview skin shapes
-    // This is really not efficient as we re-create every circle at every frame....
-    let skins = skins.into_iter();
-
-    for skin in skins {
-        commands.entity(skin).despawn();
-    }
-
-    for (squeleton, mut path) in squeleton.iter_mut() {
-        // let t = (time.elapsed_seconds().sin() + 1.) / 2.;
-        //     if let Some(head) = squeleton.nodes.first_mut() {
-        //         head.0.x = bezier.position(t).x;
-        //         head.0.y = bezier.position(t).y;
-        //     }
-
-        let points = Vec::<Vec2>::new();
-
-        let points = squeleton.skin.clone();
-
-        for point in &points {
-            gizmos.circle_2d(*point, 2.0, COLOR_WHITE);
-        }
-
-        let shape = shapes::Polygon {
-            points: points.into_iter().collect(),
-            closed: false,
-        };
-
-        *path = GeometryBuilder::build_as(&shape);
-    }
+/// How the skin outline is drawn around the skin points, chosen by the species
+#[derive(Component, Clone, Copy, Debug, Default, Deserialize)]
+pub enum SkinOutline {
+    /// Straight lines between the skin points
+    #[default]
+    Polygon,
+    /// Closed cardinal spline through the skin points, sampled `samples` times
+    /// between two points. A tension of 0.5 gives a Catmull-Rom spline.
+    Spline { tension: f32, samples: usize },
 }
@@ -0,0 +1,87 @@
+/* SPDX-License-Identifier: MIT
+* Copyright (c) 2024 Louis Mayencourt
+*/
+
+use bevy::{
+    prelude::*,
+    render::{
+        mesh::{Indices, PrimitiveTopology, VertexAttributeValues},
+        render_asset::RenderAssetUsages,
+    },
+    sprite::Mesh2dHandle,
+};
+use kinematics::outline;
+
+use crate::creatures::interpolation::RenderedChain;
+use crate::creatures::SkinOutline;
+
+/// Skin drawn as a mesh owned by the creature.
+///
+/// The mesh is created once, then its vertices are rewritten in place from
+/// the skin points of the rendered chain at every frame.
+#[derive(Component)]
+pub struct SkinMesh {
+    pub color: Color,
+}
+
+pub fn spawn_skin_mesh(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    skins: Query<(Entity, &SkinMesh), Without<Mesh2dHandle>>,
+) {
+    for (entity, skin) in skins.iter() {
+        let mesh = Mesh::new(
+            PrimitiveTopology::TriangleList,
+            RenderAssetUsages::RENDER_WORLD | RenderAssetUsages::MAIN_WORLD,
+        )
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, Vec::<[f32; 3]>::new())
+        .with_inserted_indices(Indices::U32(Vec::new()));
+
+        commands.entity(entity).insert((
+            Mesh2dHandle(meshes.add(mesh)),
+            materials.add(skin.color),
+        ));
+    }
+}
+
+pub fn update_skin_mesh(
+    mut meshes: ResMut<Assets<Mesh>>,
+    skins: Query<(&RenderedChain, &Mesh2dHandle, Option<&SkinOutline>), With<SkinMesh>>,
+) {
+    for (squeleton, handle, outline) in skins.iter() {
+        let Some(mesh) = meshes.get_mut(&handle.0) else {
+            continue;
+        };
+        if squeleton.skin.is_empty() {
+            continue;
+        }
+
+        let head = squeleton.skin.len() - 1;
+        let tail = squeleton.skin_tail_index();
+        let (points, samples) = match outline.copied().unwrap_or_default() {
+            SkinOutline::Polygon => (squeleton.skin.clone(), 1),
+            SkinOutline::Spline { tension, samples } => (
+                outline::smooth_outline(&squeleton.skin, tension, samples),
+                samples.max(1),
+            ),
+        };
+
+        let resized = match mesh.attribute_mut(Mesh::ATTRIBUTE_POSITION) {
+            Some(VertexAttributeValues::Float32x3(positions)) => {
+                let resized = positions.len() != points.len();
+                positions.clear();
+                positions.extend(points.iter().map(|point| [point.x, point.y, 0.0]));
+                resized
+            }
+            _ => continue,
+        };
+
+        // The triangles only change with the number of skin points
+        if resized {
+            if let Some(Indices::U32(indices)) = mesh.indices_mut() {
+                *indices = outline::triangulate(points.len(), head * samples, tail * samples);
+            }
+        }
+    }
+}
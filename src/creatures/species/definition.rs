@@ -0,0 +1,487 @@
+/* SPDX-License-Identifier: MIT
+* Copyright (c) 2024 Louis Mayencourt
+*/
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+    sprite::{MaterialMesh2dBundle, Mesh2dHandle},
+};
+use rand::prelude::*;
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::corbusier_colors;
+use crate::corbusier_colors::PaletteColor;
+use crate::creatures::body_parts::*;
+use crate::creatures::body_parts::eye::{spawn_eye, Blink, LookAt};
+use crate::creatures::body_parts::fin::{Fin, Flap};
+use crate::creatures::body_parts::leg::{Gait, GaitPattern, Leg};
+use crate::creatures::body_parts::tail_fin::TailFin;
+use crate::creatures::body_parts::tentacle::Tentacle;
+use crate::creatures::body_parts::tongue::{tongue_mesh, Tongue};
+use crate::creatures::interpolation::{Interpolated, RenderedChain};
+use crate::creatures::kinematic_chain::{JointLimit, KinematicChain};
+use crate::creatures::skin_mesh::SkinMesh;
+use crate::creatures::undulation::Undulation;
+use crate::creatures::SkinOutline;
+use crate::simulation_rng::EntityRng;
+
+/// Description of a creature, loaded from a `.species.ron` file
+#[derive(Asset, TypePath, Debug, Deserialize)]
+pub struct SpeciesDefinition {
+    pub spine: SpineDefinition,
+    /// Body colors, one is picked at random for each creature
+    pub palette: Vec<PaletteColor>,
+    /// What the eyes are looking at
+    #[serde(default = "default_look_at")]
+    pub look_at: LookAt,
+    /// Order in which the legs step, if the creature has legs
+    #[serde(default)]
+    pub gait: Option<GaitPattern>,
+    /// Amplitude and frequency of the side to side wave of the head
+    #[serde(default)]
+    pub undulation: Option<(f32, f32)>,
+    /// The eyes close from time to time
+    #[serde(default = "default_blink")]
+    pub blink: bool,
+    /// How the skin is drawn around the spine
+    #[serde(default = "default_outline")]
+    pub outline: SkinOutline,
+    #[serde(default)]
+    pub parts: Vec<BodyPartDefinition>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub enum SpineProfile {
+    /// Head rising from the nose, then a body decreasing in size
+    Fish,
+    /// Radius of the nodes matching the length of their segment
+    Uniform,
+    /// Radius of the nodes relative to the segment size, interpolated from head to tail
+    Radius(Vec<f32>),
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SpineDefinition {
+    pub profile: SpineProfile,
+    pub segments: usize,
+    /// Range of the segment length, picked at random for each creature
+    pub size: (f32, f32),
+    /// Relative length of the segments, interpolated from head to tail
+    #[serde(default)]
+    pub lengths: Vec<f32>,
+    /// Symmetric bend limit of the head and tail joints, in degrees
+    #[serde(default = "default_limits")]
+    pub limits: (f32, f32),
+}
+
+fn default_limits() -> (f32, f32) {
+    (20.0, 20.0)
+}
+
+fn default_radius() -> f32 {
+    1.0
+}
+
+fn default_pupil() -> PaletteColor {
+    PaletteColor::Black
+}
+
+fn default_look_at() -> LookAt {
+    LookAt::Cursor
+}
+
+fn default_blink() -> bool {
+    true
+}
+
+fn default_outline() -> SkinOutline {
+    SkinOutline::Spline {
+        tension: 0.5,
+        samples: 8,
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub enum BodyPartKind {
+    Fin,
+    /// Caudal fin bending with the spine, `size` is its length and half spread in degrees
+    TailFin,
+    Eye,
+    /// Chain stepping with the gait of the creature, described by its `limb`
+    Leg,
+    /// Forked tongue flicking out of the mouth, `size` is its length and width
+    Tongue,
+    /// Chain trailing behind the body and grabbing around, described by its `limb`
+    Tentacle,
+}
+
+/// Chain of a leg or a tentacle, from its tip to the body
+#[derive(Debug, Deserialize)]
+pub struct LimbDefinition {
+    pub segments: usize,
+    /// Length of each segment
+    pub length: f32,
+    /// Radius of the nodes, interpolated from the tip to the body
+    pub radius: Vec<f32>,
+    /// Bend limit of the joints in degrees, mirrored on the left side
+    pub limits: (f32, f32),
+}
+
+impl LimbDefinition {
+    fn chain(&self, position: BodyPartPosition) -> KinematicChain {
+        let last = self.segments.saturating_sub(1).max(1) as f32;
+        let radius = (0..self.segments)
+            .map(|i| length_scale(&self.radius, i as f32 / last))
+            .collect();
+        let limit = JointLimit::new(self.limits.0, self.limits.1);
+        let limit = if position.is_left() { limit.mirrored() } else { limit };
+
+        KinematicChain::with_profile(vec![self.length; self.segments], radius, None)
+            .expect("one radius per segment of the limb")
+            .with_limits(vec![limit])
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BodyPartDefinition {
+    pub kind: BodyPartKind,
+    pub anchor: usize,
+    pub position: BodyPartPosition,
+    /// Distance from the node, as a fraction of its radius
+    #[serde(default = "default_radius")]
+    pub radius: f32,
+    /// Rotation in degrees, positive sweeps the part toward the tail
+    #[serde(default)]
+    pub rotation: f32,
+    /// Depth of the part, positive is drawn over the skin
+    #[serde(default)]
+    pub layer: f32,
+    /// Amplitude of the fin beat at full speed, in degrees
+    #[serde(default)]
+    pub flap: Option<f32>,
+    /// Half width and half height of the ellipse
+    #[serde(default)]
+    pub size: (f32, f32),
+    /// Color of the part, the body color when not given
+    #[serde(default)]
+    pub color: Option<PaletteColor>,
+    /// Color of the pupil of the eyes
+    #[serde(default = "default_pupil")]
+    pub pupil: PaletteColor,
+    /// Chain of the legs and tentacles
+    #[serde(default)]
+    pub limb: Option<LimbDefinition>,
+}
+
+/// Creature built from a species definition.
+///
+/// The body is built once the definition is loaded and the creature got its
+/// random stream, then rebuilt every time the definition is modified.
+#[derive(Component)]
+pub struct Species(pub Handle<SpeciesDefinition>);
+
+#[derive(Debug, Error)]
+pub enum SpeciesLoaderError {
+    #[error("Could not read the species file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not parse the species file: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+    #[error("Body part anchor {0} is outside of the spine (1..{1})")]
+    InvalidAnchor(usize, usize),
+    #[error("Body part {0:?} needs a limb")]
+    MissingLimb(BodyPartKind),
+}
+
+#[derive(Default)]
+pub struct SpeciesDefinitionLoader;
+
+impl AssetLoader for SpeciesDefinitionLoader {
+    type Asset = SpeciesDefinition;
+    type Settings = ();
+    type Error = SpeciesLoaderError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        SpeciesDefinition::from_bytes(&bytes)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["species.ron"]
+    }
+}
+
+impl SpeciesDefinition {
+    /// Parse a `.species.ron` file, and check its body parts fit on the spine
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SpeciesLoaderError> {
+        let definition = ron::de::from_bytes::<SpeciesDefinition>(bytes)?;
+
+        // Body parts are oriented with the node before their anchor
+        let segments = definition.spine.segments;
+        if let Some(part) = definition
+            .parts
+            .iter()
+            .find(|part| part.anchor == 0 || part.anchor >= segments)
+        {
+            return Err(SpeciesLoaderError::InvalidAnchor(part.anchor, segments));
+        }
+        if let Some(part) = definition
+            .parts
+            .iter()
+            .find(|part| {
+                matches!(part.kind, BodyPartKind::Leg | BodyPartKind::Tentacle) && part.limb.is_none()
+            })
+        {
+            return Err(SpeciesLoaderError::MissingLimb(part.kind));
+        }
+
+        Ok(definition)
+    }
+
+    /// Build a spine with a random size within the species range
+    pub fn spine(&self, rng: &mut impl Rng) -> KinematicChain {
+        let spine = &self.spine;
+        let (min, max) = spine.size;
+        let size = if min < max {
+            rng.gen_range(min..max)
+        } else {
+            min
+        };
+
+        let last = spine.segments.saturating_sub(1).max(1) as f32;
+        let lengths = (0..spine.segments)
+            .map(|i| size * length_scale(&spine.lengths, i as f32 / last))
+            .collect();
+
+        let chain = match &spine.profile {
+            SpineProfile::Fish => KinematicChain::fish_like_with_lengths(lengths, None),
+            SpineProfile::Uniform => KinematicChain::with_lengths(lengths, None),
+            SpineProfile::Radius(profile) => {
+                let radius = (0..spine.segments)
+                    .map(|i| size * length_scale(profile, i as f32 / last))
+                    .collect();
+                let mut chain = KinematicChain::with_profile(lengths, radius, None)
+                    .expect("one radius per segment of the spine");
+                chain.target = Vec3::new(0.0, chain.length(), 0.0);
+                chain
+            }
+        };
+        chain.with_limits_profile(
+            JointLimit::symmetric(spine.limits.0),
+            JointLimit::symmetric(spine.limits.1),
+        )
+    }
+
+    /// Random color of the palette, or of the whole corbusier palette if empty
+    pub fn color(&self, rng: &mut impl Rng) -> Color {
+        self.palette
+            .choose(rng)
+            .map(|color| (*color).into())
+            .unwrap_or_else(|| corbusier_colors::random(rng))
+    }
+
+    /// Spawn the body parts, the ones without color take the `body` color
+    pub fn spawn_parts(
+        &self,
+        parent: &mut ChildBuilder,
+        meshes: &mut Assets<Mesh>,
+        materials: &mut Assets<ColorMaterial>,
+        body: Color,
+        rng: &mut impl Rng,
+    ) {
+        for (index, part) in self.parts.iter().enumerate() {
+            let anchor = BodyPartAnchor::new(part.anchor, part.position)
+                .with_radius(part.radius)
+                .with_rotation(part.rotation)
+                .with_layer(part.layer);
+            let size = Vec2::new(part.size.0, part.size.1);
+            let color = part.color.map_or(body, Color::from);
+
+            match part.kind {
+                BodyPartKind::Fin => {
+                    let mut fin = parent.spawn((
+                        Fin,
+                        anchor,
+                        MaterialMesh2dBundle {
+                            mesh: Mesh2dHandle(meshes.add(Ellipse::new(size.x, size.y))),
+                            material: materials.add(color),
+                            ..default()
+                        },
+                    ));
+                    if let Some(amplitude) = part.flap {
+                        fin.insert(Flap::new(amplitude));
+                    }
+                }
+                BodyPartKind::TailFin => {
+                    let tail_fin = TailFin::new(size.x, size.y);
+                    parent.spawn((
+                        anchor,
+                        MaterialMesh2dBundle {
+                            mesh: Mesh2dHandle(meshes.add(tail_fin.mesh())),
+                            material: materials.add(color),
+                            ..default()
+                        },
+                        tail_fin,
+                    ));
+                }
+                BodyPartKind::Eye => {
+                    spawn_eye(parent, meshes, materials, anchor, size, color, part.pupil.into());
+                }
+                BodyPartKind::Tongue => {
+                    // Flick at its own time, drawn from the stream of the creature
+                    let mut tongue = Tongue::new(0.0);
+                    tongue.phase = rng.gen_range(0.0..tongue.period);
+                    parent.spawn((
+                        anchor,
+                        tongue,
+                        MaterialMesh2dBundle {
+                            mesh: Mesh2dHandle(meshes.add(tongue_mesh(size.x, size.y))),
+                            material: materials.add(color),
+                            ..default()
+                        },
+                    ));
+                }
+                BodyPartKind::Leg | BodyPartKind::Tentacle => {
+                    let Some(limb) = &part.limb else {
+                        continue;
+                    };
+                    let mut limb = parent.spawn((
+                        anchor,
+                        limb.chain(part.position),
+                        SkinMesh { color },
+                        SpatialBundle::default(),
+                    ));
+                    match part.kind {
+                        BodyPartKind::Leg => limb.insert(Leg::default()),
+                        // Out of phase, to not curl all the tentacles together
+                        _ => limb.insert(Tentacle::new(index as f32)),
+                    };
+                }
+            };
+        }
+    }
+}
+
+/// Interpolate the relative length of a segment at `t`, from 0 at the head to 1 at the tail
+fn length_scale(profile: &[f32], t: f32) -> f32 {
+    match profile {
+        [] => 1.0,
+        [scale] => *scale,
+        _ => {
+            let position = t * (profile.len() - 1) as f32;
+            let index = (position as usize).min(profile.len() - 2);
+            let fraction = position - index as f32;
+            profile[index] + (profile[index + 1] - profile[index]) * fraction
+        }
+    }
+}
+
+/// Build the body of the creatures once their definition is loaded, and again
+/// when it is modified.
+///
+/// The body is drawn from the stream of the creature, so it doesn't depend on
+/// when the definition finished loading.
+pub fn build_species(
+    mut commands: Commands,
+    mut events: EventReader<AssetEvent<SpeciesDefinition>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    definitions: Res<Assets<SpeciesDefinition>>,
+    mut creatures: Query<(Entity, &Species, Option<&KinematicChain>, &mut EntityRng)>,
+) {
+    let modified: Vec<AssetId<SpeciesDefinition>> = events
+        .read()
+        .filter_map(|event| match event {
+            AssetEvent::Modified { id } => Some(*id),
+            _ => None,
+        })
+        .collect();
+
+    for (entity, species, previous, mut rng) in creatures.iter_mut() {
+        if previous.is_some() && !modified.contains(&species.0.id()) {
+            continue;
+        }
+        let Some(definition) = definitions.get(&species.0) else {
+            continue;
+        };
+
+        let mut spine = definition.spine(&mut *rng);
+        // Keep the creature where it was when the definition is reloaded
+        if let Some(previous) = previous {
+            if let Some(head) = previous.nodes.first() {
+                for node in spine.nodes.iter_mut() {
+                    node.0 += head.0;
+                }
+            }
+            spine.target = previous.target;
+        }
+
+        let color = definition.color(&mut *rng);
+        let mut creature = commands.entity(entity);
+        creature
+            .despawn_descendants()
+            .remove::<(Mesh2dHandle, Gait, Blink, Undulation)>()
+            .insert((
+                // Forget the positions of the previous body
+                Interpolated::default(),
+                RenderedChain::new(spine.clone()),
+                spine,
+                SkinMesh { color },
+                definition.outline,
+                definition.look_at,
+                SpatialBundle::default(),
+            ))
+            .with_children(|parent| {
+                definition.spawn_parts(parent, &mut meshes, &mut materials, color, &mut *rng);
+            });
+        if let Some(pattern) = definition.gait {
+            creature.insert(Gait::new(pattern));
+        }
+        if let Some((amplitude, frequency)) = definition.undulation {
+            creature.insert(Undulation::new(amplitude, frequency));
+        }
+        if definition.blink {
+            creature.insert(Blink::default());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SPECIES: [(&str, &str); 4] = [
+        ("fish", include_str!("../../../assets/species/fish.species.ron")),
+        ("lizard", include_str!("../../../assets/species/lizard.species.ron")),
+        ("snake", include_str!("../../../assets/species/snake.species.ron")),
+        ("octopus", include_str!("../../../assets/species/octopus.species.ron")),
+    ];
+
+    #[test]
+    fn shipped_species_are_valid() {
+        let mut rng = StdRng::seed_from_u64(0);
+        for (name, file) in SPECIES {
+            let definition = SpeciesDefinition::from_bytes(file.as_bytes())
+                .unwrap_or_else(|error| panic!("{name}: {error}"));
+            let spine = definition.spine(&mut rng);
+            assert_eq!(spine.nodes.len(), definition.spine.segments, "{name}");
+        }
+    }
+
+    #[test]
+    fn legs_need_a_limb() {
+        let file = "(spine: (profile: Uniform, segments: 4, size: (10.0, 10.0)), palette: [], \
+            parts: [(kind: Leg, anchor: 2, position: Left)])";
+        assert!(matches!(
+            SpeciesDefinition::from_bytes(file.as_bytes()),
+            Err(SpeciesLoaderError::MissingLimb(BodyPartKind::Leg))
+        ));
+    }
+}
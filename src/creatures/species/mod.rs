@@ -2,4 +2,4 @@
 * Copyright (c) 2024 Louis Mayencourt
 */
 
-pub mod fish;
\ No newline at end of file
+pub mod definition;
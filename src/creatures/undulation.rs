@@ -0,0 +1,65 @@
+/* SPDX-License-Identifier: MIT
+* Copyright (c) 2024 Louis Mayencourt
+*/
+
+use bevy::prelude::*;
+
+use crate::creatures::kinematic_chain::KinematicChain;
+
+/// Side to side wave of the head, dragging the body in a S shape.
+///
+/// The head target is pushed perpendicular to the direction of travel, so the
+/// creature can still follow a path.
+#[derive(Component)]
+pub struct Undulation {
+    /// Distance of the head from the path at the top of the wave
+    pub amplitude: f32,
+    /// Waves per second
+    pub frequency: f32,
+    pub phase: f32,
+    /// Target set by the path, before the wave is added
+    last_target: Option<Vec3>,
+    /// Offset added to the target on the last frame
+    offset: Vec3,
+}
+
+impl Undulation {
+    pub fn new(amplitude: f32, frequency: f32) -> Self {
+        Undulation {
+            amplitude,
+            frequency,
+            phase: 0.0,
+            last_target: None,
+            offset: Vec3::ZERO,
+        }
+    }
+}
+
+/// Distance under which the target is considered left where the wave put it
+const UNTOUCHED_DISTANCE: f32 = 1e-3;
+
+/// Add the side to side wave to the head target
+pub fn undulate(time: Res<Time>, mut query: Query<(&mut KinematicChain, &mut Undulation)>) {
+    for (mut squeleton, mut undulation) in query.iter_mut() {
+        // The target is left untouched when the creature doesn't follow anything
+        let mut target = squeleton.target;
+        let untouched = |last: Vec3| (last + undulation.offset).distance(target) < UNTOUCHED_DISTANCE;
+        if undulation.last_target.is_some_and(untouched) {
+            target -= undulation.offset;
+        }
+        let Some(last_target) = undulation.last_target.replace(target) else {
+            continue;
+        };
+
+        let direction = squeleton.space.project(target - last_target).normalize_or_zero();
+        if direction == Vec3::ZERO {
+            continue;
+        }
+
+        undulation.phase += time.delta_seconds() * undulation.frequency * std::f32::consts::TAU;
+        undulation.phase %= std::f32::consts::TAU;
+        let side = squeleton.space.axis().cross(direction);
+        undulation.offset = side * undulation.amplitude * undulation.phase.sin();
+        squeleton.target = target + undulation.offset;
+    }
+}
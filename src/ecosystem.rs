@@ -0,0 +1,274 @@
+/* SPDX-License-Identifier: MIT
+* Copyright (c) 2024 Louis Mayencourt
+*/
+
+//! Creatures getting hungry, hunting the smaller ones and eating the food
+//! pellets dropped by the player.
+
+use std::collections::HashSet;
+
+use bevy::{
+    prelude::*,
+    sprite::{MaterialMesh2dBundle, Mesh2dHandle},
+};
+
+use crate::corbusier_colors::*;
+use crate::creatures::{kinematic_chain::KinematicChain, Creature, Playable, SimulationSet};
+use crate::flocking::{flock, Flocking};
+use crate::steering::{self, steer, Steering};
+use crate::MyWorldCoords;
+
+/// A predator must be this much bigger than its prey
+const PREDATOR_RATIO: f32 = 1.2;
+/// Radius of a food pellet
+const PELLET_RADIUS: f32 = 4.0;
+/// Creatures don't grow past this body size
+const MAX_BODY_SIZE: f32 = 800.0;
+/// Hunting and fleeing creatures go faster than their usual pace
+const RUSH: f32 = 1.3;
+
+pub struct EcosystemPlugin;
+
+impl Plugin for EcosystemPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, drop_food);
+        app.add_systems(FixedUpdate, hunt.after(flock).after(steer).in_set(SimulationSet::Target));
+    }
+}
+
+/// What a creature eats
+#[allow(dead_code)]
+#[derive(Component, Clone, Copy, PartialEq)]
+pub enum Diet {
+    /// Only the food pellets
+    Herbivore,
+    /// Only the smaller creatures
+    Carnivore,
+    Omnivore,
+}
+
+impl Diet {
+    fn eats_food(&self) -> bool {
+        matches!(self, Diet::Herbivore | Diet::Omnivore)
+    }
+
+    fn eats_creatures(&self) -> bool {
+        matches!(self, Diet::Carnivore | Diet::Omnivore)
+    }
+}
+
+/// Need of food, growing over time
+#[derive(Component)]
+pub struct Hunger {
+    /// From 0 when full to 1 when starving
+    pub value: f32,
+    /// Increase of the hunger per second
+    pub rate: f32,
+    /// Hunger from which the creature looks for food
+    pub threshold: f32,
+}
+
+impl Hunger {
+    pub fn new(value: f32) -> Self {
+        Hunger {
+            value,
+            rate: 0.02,
+            threshold: 0.5,
+        }
+    }
+
+    fn is_hungry(&self) -> bool {
+        self.value > self.threshold
+    }
+}
+
+/// Distance up to which a creature sees its prey and predators
+#[derive(Component)]
+pub struct Perception {
+    pub radius: f32,
+}
+
+/// Food pellet, eaten by the creatures with a diet of food
+#[derive(Component)]
+pub struct Food {
+    /// Hunger removed when eaten
+    pub nutrition: f32,
+}
+
+/// Drop a food pellet under the cursor with the right click
+fn drop_food(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    buttons: Res<ButtonInput<MouseButton>>,
+    cursor: Res<MyWorldCoords>,
+) {
+    if buttons.just_pressed(MouseButton::Right) {
+        commands.spawn((
+            Food { nutrition: 0.3 },
+            MaterialMesh2dBundle {
+                mesh: Mesh2dHandle(meshes.add(Circle::new(PELLET_RADIUS))),
+                material: materials.add(COLOR_GREEN),
+                transform: Transform::from_translation(cursor.0.extend(-0.5)),
+                ..default()
+            },
+        ));
+    }
+}
+
+/// What a creature wants to do this frame
+enum Urge {
+    Flee(Vec3),
+    Hunt(Entity, Vec3),
+    Eat(Entity, Vec3),
+}
+
+#[allow(clippy::type_complexity)]
+fn hunt(
+    mut commands: Commands,
+    time: Res<Time>,
+    food: Query<(Entity, &Transform, &Food)>,
+    mut creatures: Query<
+        (
+            Entity,
+            &mut KinematicChain,
+            Option<&Diet>,
+            Option<&Perception>,
+            Option<&mut Hunger>,
+            Option<&mut Flocking>,
+            Option<&mut Steering>,
+            Has<Playable>,
+        ),
+        With<Creature>,
+    >,
+) {
+    let delta = time.delta_seconds();
+
+    // Head position, head radius, size and diet of all the creatures. Only the
+    // creatures of the ecosystem can be eaten, not the player or the decor ones
+    let bodies: Vec<(Entity, Vec3, f32, f32, Option<Diet>, bool)> = creatures
+        .iter()
+        .filter_map(|(entity, chain, diet, perception, .., playable)| {
+            let edible = diet.is_some() && perception.is_some() && !playable;
+            chain
+                .nodes
+                .first()
+                .map(|head| (entity, head.0, head.1, chain.body_size(), diet.copied(), edible))
+        })
+        .collect();
+    let mut eaten = HashSet::new();
+
+    for (entity, mut chain, diet, perception, hunger, flocking, steering, _) in creatures.iter_mut() {
+        // Already eaten by a creature earlier this step
+        if eaten.contains(&entity) {
+            continue;
+        }
+        let (Some(perception), Some(head)) = (perception, chain.nodes.first().copied()) else {
+            continue;
+        };
+        let size = chain.body_size();
+        let in_sight = |position: Vec3| position.distance(head.0) < perception.radius;
+
+        // Run away from the predators first, then look for food when hungry
+        let threat = bodies
+            .iter()
+            .filter(|(other, position, _, other_size, other_diet, _)| {
+                *other != entity
+                    && other_diet.is_some_and(|diet| diet.eats_creatures())
+                    && *other_size > size * PREDATOR_RATIO
+                    && in_sight(*position)
+            })
+            .min_by(|a, b| a.1.distance(head.0).total_cmp(&b.1.distance(head.0)));
+
+        let mut hunger = hunger;
+        if let Some(hunger) = hunger.as_mut() {
+            hunger.value = (hunger.value + hunger.rate * delta).min(1.0);
+        }
+        let hungry = hunger.as_ref().is_some_and(|hunger| hunger.is_hungry());
+        let diet = diet.copied();
+
+        let urge = if let Some((_, position, ..)) = threat {
+            Some(Urge::Flee(*position))
+        } else if hungry {
+            let prey = bodies
+                .iter()
+                .filter(|(other, position, _, other_size, _, edible)| {
+                    diet.is_some_and(|diet| diet.eats_creatures())
+                        && *edible
+                        && *other != entity
+                        && !eaten.contains(other)
+                        && size > *other_size * PREDATOR_RATIO
+                        && in_sight(*position)
+                })
+                .map(|(other, position, ..)| Urge::Hunt(*other, *position));
+            let pellets = food
+                .iter()
+                .filter(|(pellet, transform, _)| {
+                    diet.is_some_and(|diet| diet.eats_food())
+                        && !eaten.contains(pellet)
+                        && in_sight(transform.translation)
+                })
+                .map(|(pellet, transform, _)| Urge::Eat(pellet, transform.translation));
+
+            prey.chain(pellets).min_by(|a, b| {
+                let distance = |urge: &Urge| match urge {
+                    Urge::Flee(position) | Urge::Hunt(_, position) | Urge::Eat(_, position) => {
+                        position.distance(head.0)
+                    }
+                };
+                distance(a).total_cmp(&distance(b))
+            })
+        } else {
+            None
+        };
+
+        let Some(urge) = urge else {
+            continue;
+        };
+
+        // Take over the velocity given by the flock or the steering behaviours
+        let (velocity, max_speed) = match (flocking, steering) {
+            (Some(flocking), _) => {
+                let max_speed = flocking.max_speed;
+                (flocking.map_unchanged(|flocking| &mut flocking.velocity), max_speed)
+            }
+            (None, Some(steering)) => {
+                let max_speed = steering.max_speed;
+                (steering.map_unchanged(|steering| &mut steering.velocity), max_speed)
+            }
+            (None, None) => continue,
+        };
+        let mut velocity = velocity;
+        let speed = max_speed * RUSH;
+
+        let (desired, meal) = match urge {
+            Urge::Flee(position) => (steering::flee(head.0, position, speed), None),
+            Urge::Hunt(prey, position) => {
+                let radius = bodies.iter().find(|body| body.0 == prey).map_or(0.0, |body| body.2);
+                let caught = position.distance(head.0) < head.1 + radius;
+                (steering::seek(head.0, position, speed), caught.then_some((prey, 0.5, 1.05)))
+            }
+            Urge::Eat(pellet, position) => {
+                let nutrition = food.get(pellet).map_or(0.0, |(_, _, food)| food.nutrition);
+                let caught = position.distance(head.0) < head.1 + PELLET_RADIUS;
+                (steering::seek(head.0, position, speed), caught.then_some((pellet, nutrition, 1.02)))
+            }
+        };
+
+        // Turn toward the desired velocity within a fraction of a second
+        let ease = 1.0 - (-4.0 * delta).exp();
+        *velocity = chain.space.project(*velocity + (desired - *velocity) * ease);
+        chain.target = head.0 + *velocity * delta;
+
+        if let Some((meal, nutrition, growth)) = meal {
+            eaten.insert(meal);
+            commands.entity(meal).despawn_recursive();
+            if let Some(hunger) = hunger.as_mut() {
+                hunger.value = (hunger.value - nutrition).max(0.0);
+            }
+            if size < MAX_BODY_SIZE {
+                chain.grow(growth);
+            }
+        }
+    }
+}
@@ -0,0 +1,168 @@
+/* SPDX-License-Identifier: MIT
+* Copyright (c) 2024 Louis Mayencourt
+*/
+
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use rand::prelude::*;
+
+use crate::creatures::{kinematic_chain::KinematicChain, SimulationSet};
+
+pub struct FlockingPlugin;
+
+impl Plugin for FlockingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<FlockingSettings>();
+        app.add_systems(FixedUpdate, flock.in_set(SimulationSet::Target));
+    }
+}
+
+/// Weights of the flocking rules, shared by all the creatures
+#[derive(Resource)]
+pub struct FlockingSettings {
+    /// Distance up to which the other creatures are part of the flock
+    pub neighbour_radius: f32,
+    /// Distance under which the creatures move away from each other
+    pub separation_radius: f32,
+    pub separation: f32,
+    pub alignment: f32,
+    pub cohesion: f32,
+    /// Pull back inside `bounds`
+    pub containment: f32,
+    pub bounds: Rect,
+}
+
+impl Default for FlockingSettings {
+    fn default() -> Self {
+        FlockingSettings {
+            neighbour_radius: 120.0,
+            separation_radius: 40.0,
+            separation: 1.5,
+            alignment: 1.0,
+            cohesion: 0.8,
+            containment: 3.0,
+            bounds: Rect::new(-600.0, -300.0, 600.0, 300.0),
+        }
+    }
+}
+
+/// Creature swimming with its neighbours, like a school of fish
+#[derive(Component)]
+pub struct Flocking {
+    pub velocity: Vec3,
+    pub max_speed: f32,
+}
+
+impl Flocking {
+    /// Start swimming in a random direction
+    pub fn random(max_speed: f32, rng: &mut impl Rng) -> Self {
+        let angle = rng.gen_range(0.0..std::f32::consts::TAU);
+        Flocking {
+            velocity: Vec2::from_angle(angle).extend(0.0) * max_speed,
+            max_speed,
+        }
+    }
+}
+
+/// Positions sorted in square cells, to only look for neighbours in the cells around
+struct SpatialHash {
+    cell_size: f32,
+    cells: HashMap<IVec2, Vec<usize>>,
+}
+
+impl SpatialHash {
+    fn new(cell_size: f32, positions: &[Vec3]) -> Self {
+        let mut hash = SpatialHash {
+            cell_size: cell_size.max(1.0),
+            cells: HashMap::new(),
+        };
+        for (i, position) in positions.iter().enumerate() {
+            let cell = hash.cell(*position);
+            hash.cells.entry(cell).or_default().push(i);
+        }
+        hash
+    }
+
+    fn cell(&self, position: Vec3) -> IVec2 {
+        (position.truncate() / self.cell_size).floor().as_ivec2()
+    }
+
+    /// Indices of the positions in the cell of `position` and the 8 around it
+    fn around(&self, position: Vec3) -> impl Iterator<Item = usize> + '_ {
+        let center = self.cell(position);
+        (-1..=1)
+            .flat_map(move |x| (-1..=1).map(move |y| center + IVec2::new(x, y)))
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            .copied()
+    }
+}
+
+/// Steering toward `direction` at full speed
+fn steer(direction: Vec3, velocity: Vec3, max_speed: f32) -> Vec3 {
+    direction.normalize_or_zero() * max_speed - velocity
+}
+
+pub fn flock(
+    time: Res<Time>,
+    settings: Res<FlockingSettings>,
+    mut query: Query<(&mut KinematicChain, &mut Flocking)>,
+) {
+    let delta = time.delta_seconds();
+
+    let boids: Vec<(Vec3, Vec3)> = query
+        .iter()
+        .filter_map(|(chain, flocking)| chain.nodes.first().map(|head| (head.0, flocking.velocity)))
+        .collect();
+    let positions: Vec<Vec3> = boids.iter().map(|boid| boid.0).collect();
+    let hash = SpatialHash::new(settings.neighbour_radius, &positions);
+
+    for (i, (mut chain, mut flocking)) in query.iter_mut().filter(|(chain, _)| !chain.nodes.is_empty()).enumerate() {
+        let (position, velocity) = boids[i];
+        let space = chain.space;
+
+        let mut away = Vec3::ZERO;
+        let mut heading = Vec3::ZERO;
+        let mut center = Vec3::ZERO;
+        let mut neighbours = 0;
+        for j in hash.around(position).filter(|j| *j != i) {
+            let (other, other_velocity) = boids[j];
+            let offset = space.project(position - other);
+            let distance = offset.length();
+            if distance > settings.neighbour_radius {
+                continue;
+            }
+
+            // Closer neighbours push harder
+            if distance < settings.separation_radius && distance > 0.0 {
+                away += offset / distance * (1.0 - distance / settings.separation_radius);
+            }
+            heading += other_velocity;
+            center += other;
+            neighbours += 1;
+        }
+
+        let max_speed = flocking.max_speed;
+        let mut acceleration = Vec3::ZERO;
+        if neighbours > 0 {
+            center /= neighbours as f32;
+            acceleration += steer(away, velocity, max_speed) * settings.separation * away.length().min(1.0);
+            acceleration += steer(heading, velocity, max_speed) * settings.alignment;
+            acceleration += steer(space.project(center - position), velocity, max_speed) * settings.cohesion;
+        }
+
+        // Come back when leaving the bounds
+        let bounds = settings.bounds;
+        if !bounds.contains(position.truncate()) {
+            let inside = (bounds.center() - position.truncate()).extend(0.0);
+            acceleration += steer(inside, velocity, max_speed) * settings.containment;
+        }
+
+        // Keep a minimum speed, so the flock doesn't stall
+        let velocity = space.project(velocity + acceleration * delta)
+            .clamp_length(max_speed * 0.5, max_speed);
+        flocking.velocity = velocity;
+        chain.target = position + velocity * delta;
+    }
+}
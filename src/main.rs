@@ -4,11 +4,13 @@
 
 use bevy::{
     prelude::*,
+    sprite::{MaterialMesh2dBundle, Mesh2dHandle},
     window::{PrimaryWindow, WindowResized},
     diagnostic::FrameTimeDiagnosticsPlugin,
 };
 use bevy_embedded_assets::EmbeddedAssetPlugin;
 use bevy_prototype_lyon::prelude::*;
+use rand::Rng;
 
 mod audio;
 mod corbusier_colors;
@@ -16,13 +18,33 @@ mod creatures;
 mod fps_counter;
 mod water_effect;
 mod path;
+mod svg_path;
+mod flocking;
+mod steering;
+mod ecosystem;
+mod simulation_rng;
+#[cfg(test)]
+mod test_support;
 
 use audio::AudioPlugin;
-use creatures::{kinematic_chain::KinematicChain, Creature, Playable, CreaturesPlugin};
+use creatures::{kinematic_chain::{KinematicChain, Locomotion}, Creature, Playable, CreaturesPlugin};
+use creatures::species::definition::{Species, SpeciesDefinition};
 use water_effect::{WaterEffectPlugin, TextureCamera};
 use fps_counter::FpsDisplay;
 use corbusier_colors::*;
 use path::*;
+use flocking::{Flocking, FlockingPlugin};
+use steering::{Behaviour, Obstacle, Steering, SteeringPlugin};
+use ecosystem::{Diet, EcosystemPlugin, Hunger, Perception};
+use simulation_rng::{SimulationRng, SimulationRngPlugin};
+
+/// Source of the species and paths. They are embedded in the binary like the
+/// other assets, or read from the `assets` directory to hot reload them while
+/// developing.
+#[cfg(not(feature = "dev"))]
+const SOURCE: &str = "embedded://";
+#[cfg(feature = "dev")]
+const SOURCE: &str = "";
 
 /// We will store the world position of the mouse cursor here.
 #[derive(Resource, Default)]
@@ -45,9 +67,10 @@ fn main() {
                     ..default()
                 })
             )
-        .add_plugins(FrameTimeDiagnosticsPlugin::default())
+        .add_plugins(FrameTimeDiagnosticsPlugin)
         .add_plugins(FpsDisplay)
         .add_plugins(ShapePlugin)
+        .add_plugins(SimulationRngPlugin)
         .add_plugins(CreaturesPlugin)
         .add_plugins(WaterEffectPlugin)
         .add_plugins(AudioPlugin)
@@ -59,27 +82,90 @@ fn main() {
         .add_systems(Update, adapt_windows_size)
         // .add_systems(Update, follow_circle)
         .add_plugins(PathPlugin)
+        .add_plugins(FlockingPlugin)
+        .add_plugins(SteeringPlugin)
+        .add_plugins(EcosystemPlugin)
         .run();
 }
 
 fn setup(mut commands: Commands, mut config_store: ResMut<GizmoConfigStore>,
+    asset_server: Res<AssetServer>,
     mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<ColorMaterial>>,) {
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut rng: ResMut<SimulationRng>,) {
 
     let (config, _) = config_store.config_mut::<DefaultGizmoConfigGroup>();
     config.enabled = false;
 
-    for i in 0..10 {
-        let fish = creatures::species::fish::Fish::random();
-        let entity = fish.spawn(&mut commands, &mut meshes, &mut materials);
-        let x_size = 600.0;
-        let y_size = 300.0;
-        commands.entity(entity).insert((
+    // The fish bodies are built once the species definition is loaded
+    let fish: Handle<SpeciesDefinition> = asset_server.load(format!("{SOURCE}species/fish.species.ron"));
+
+    // The fish swim in schools, the bigger ones eat the smaller ones
+    for _ in 0..30 {
+        commands.spawn((
             Creature,
-            PathComponents::new(vec![Vec3::ZERO]),
-            PathLoop::random(5, -x_size..x_size, -y_size..y_size),
+            Species(fish.clone()),
+            Flocking::random(150.0, &mut *rng),
+            Diet::Omnivore,
+            Hunger::new(rng.gen_range(0.0..0.6)),
+            Perception { radius: 150.0 },
+        ));
+    }
+
+    // Rocks on the floor, the steered creatures swim around them
+    for (x, y) in [(-300.0, 100.0), (250.0, -150.0), (50.0, 200.0)] {
+        commands.spawn((
+            Obstacle { radius: 40.0 },
+            MaterialMesh2dBundle {
+                mesh: Mesh2dHandle(meshes.add(Circle::new(40.0))),
+                material: materials.add(COLOR_BLACK),
+                transform: Transform::from_xyz(x, y, -2.0),
+                ..default()
+            },
         ));
     }
+
+    // The octopus hunts the lizard
+    let bounds = Rect::new(-600.0, -300.0, 600.0, 300.0);
+    let lizard = commands.spawn(Species(asset_server.load(format!("{SOURCE}species/lizard.species.ron")))).id();
+    let octopus = commands.spawn(Species(asset_server.load(format!("{SOURCE}species/octopus.species.ron")))).id();
+    commands.entity(lizard).insert((
+        Creature,
+        Steering::new(120.0, 300.0, 180.0)
+            .with(Behaviour::Wander { distance: 80.0, radius: 40.0, jitter: 360.0 }, 1.0)
+            .with(Behaviour::Evade { entity: octopus, range: 200.0 }, 2.0)
+            .with(Behaviour::AvoidObstacles { look_ahead: 80.0 }, 3.0)
+            .with(Behaviour::Contain(bounds), 3.0),
+    ));
+    commands.entity(octopus).insert((
+        Creature,
+        Steering::new(80.0, 150.0, 90.0)
+            .with(Behaviour::Pursue(lizard), 1.0)
+            .with(Behaviour::AvoidObstacles { look_ahead: 80.0 }, 3.0)
+            .with(Behaviour::Contain(bounds), 3.0),
+    ));
+
+    // The snake follows a figure eight drawn in svg
+    commands.spawn((
+        Creature,
+        Species(asset_server.load(format!("{SOURCE}species/snake.species.ron"))),
+        Locomotion::new(250.0, 500.0, 180.0),
+        PathComponents::new(vec![Vec3::ZERO]).with_speed(150.0),
+        SvgRoute {
+            path: asset_server.load(format!("{SOURCE}paths/figure_eight.svg")),
+            scale: 1.0,
+            offset: Vec2::new(-500.0, 250.0),
+        },
+    ));
+
+    // The playable fish follows the waypoints placed with the mouse
+    commands.spawn((
+        Creature,
+        Playable,
+        Species(fish),
+        PathComponents::new(vec![Vec3::ZERO]).with_speed(120.0).with_easing(60.0),
+        Locomotion::default(),
+    ));
 }
 
 fn my_cursor_system(
@@ -107,11 +193,11 @@ fn my_cursor_system(
     }
 }
 
+#[allow(dead_code)]
 fn follow_circle(
     time: Res<Time>,
     mut q_squeleton: Query<&mut KinematicChain, With<Playable>>,
     buttons: Res<ButtonInput<MouseButton>>,
-    touches: Res<Touches>,
     mut gizmos: Gizmos,
 ) {
     if !buttons.pressed(MouseButton::Left) {
@@ -132,7 +218,8 @@ fn follow_mouse(
     buttons: Res<ButtonInput<MouseButton>>,
     touches: Res<Touches>,
     mut mycoords: ResMut<MyWorldCoords>,
-    mut squeleton: Query<&mut KinematicChain, With<Playable>>,
+    // Playable creatures with a path are moved by the waypoint editor instead
+    mut squeleton: Query<&mut KinematicChain, (With<Playable>, Without<PathComponents>)>,
     q_window: Query<&Window, With<PrimaryWindow>>,
     q_camera: Query<(&Camera, &GlobalTransform)>,
 ) {
@@ -172,9 +259,9 @@ fn follow_mouse(
             .viewport_to_world(camera_transform, finger.position())
             .map(|ray| ray.origin.truncate())
         {
-            let mut squeleton = squeleton.single_mut();
-
-            squeleton.target = position.extend(0.0);
+            if let Ok(mut squeleton) = squeleton.get_single_mut() {
+                squeleton.target = position.extend(0.0);
+            }
         }
     }
 }
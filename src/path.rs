@@ -5,14 +5,15 @@
 use std::ops::Range;
 
 use bevy::{
+    math::cubic_splines::LinearSpline,
     prelude::*,
     sprite::{MaterialMesh2dBundle, Mesh2dHandle},
 };
-use bevy_prototype_lyon::prelude::*;
 use rand::prelude::*;
 
-use crate::creatures::{kinematic_chain::KinematicChain, Creature};
+use crate::creatures::{kinematic_chain::KinematicChain, Creature, Playable, SimulationSet};
 use crate::corbusier_colors::*;
+use crate::svg_path::{SvgPath, SvgPathLoader};
 
 use crate::MyWorldCoords;
 
@@ -21,19 +22,213 @@ pub struct PathPlugin;
 impl Plugin for PathPlugin {
     fn build(&self, app: &mut App) {
         // app.add_systems(Startup, setup);
-        app.add_systems(Update, follow_path);
-        // app.add_systems(Update, add_points);
-        app.add_systems(Update, loop_path);
+        app.init_asset::<SvgPath>();
+        app.init_asset_loader::<SvgPathLoader>();
+        app.init_resource::<WaypointEditor>();
+        app.init_gizmo_group::<WaypointGizmos>();
+        app.add_systems(Update, draw_paths);
+        app.add_systems(Update, add_points);
+        app.add_systems(Update, draw_waypoints.after(add_points));
+        // The loops slide their window before the creatures move along it
+        app.add_systems(FixedUpdate, (loop_path, follow_path).chain().in_set(SimulationSet::Target));
+        app.add_systems(Update, load_svg_routes);
     }
 }
 
-#[derive(Component)]
-struct Path(CubicCurve<Vec3>);
+/// Distance from which a waypoint can be grabbed with the mouse
+const PICK_RADIUS: f32 = 10.0;
+
+/// Waypoint of the playable creature grabbed by the mouse
+#[derive(Resource, Default)]
+struct WaypointEditor {
+    dragging: Option<usize>,
+}
+
+/// Gizmos of the waypoint editor, always shown unlike the debug ones
+#[derive(Default, Reflect, GizmoConfigGroup)]
+struct WaypointGizmos;
+
+/// Samples of the arc length table, for each segment of the curve
+const SAMPLES_PER_SEGMENT: usize = 16;
+
+/// Fraction of the speed kept at the very ends of an eased path
+const MIN_EASED_SPEED: f32 = 0.1;
+
+/// Interpolation of the curve between the points of a path
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[allow(dead_code)]
+pub enum SplineKind {
+    /// Passes through every point, `tension` scales the tangents
+    Cardinal { tension: f32 },
+    /// Smoother, but doesn't pass through the points. Needs at least 4 points
+    BSpline,
+    /// Passes through every third point, the two in between are the handles.
+    /// A cyclic curve takes the handles of its closing segment from the points
+    /// left after the last one, or mirrors the neighbouring handles when there
+    /// are none. Ending on the first point already closes the curve.
+    Bezier,
+    /// Straight lines between the points
+    Linear,
+}
+
+impl Default for SplineKind {
+    fn default() -> Self {
+        SplineKind::Cardinal { tension: 0.5 }
+    }
+}
+
+impl SplineKind {
+    /// Curve through `points`, going back to the first point when `cyclic`
+    pub fn curve(&self, points: &[Vec3], cyclic: bool) -> CubicCurve<Vec3> {
+        let count = points.len();
+        match self {
+            SplineKind::Cardinal { tension } if cyclic && count > 2 => {
+                // Same tangents as the cardinal spline, on every point of the loop
+                let tangents: Vec<Vec3> = (0..count)
+                    .map(|i| (points[(i + 1) % count] - points[(i + count - 1) % count]) * *tension)
+                    .collect();
+                CubicHermite::new(
+                    points.iter().chain(points.first()).copied(),
+                    tangents.iter().chain(tangents.first()).copied(),
+                )
+                .to_curve()
+            }
+            SplineKind::Cardinal { tension } => CubicCardinalSpline::new(*tension, points.to_vec()).to_curve(),
+            SplineKind::BSpline if cyclic && count > 2 => {
+                CubicBSpline::new([points, &points[..3]].concat()).to_curve()
+            }
+            SplineKind::BSpline => CubicBSpline::new(points.to_vec()).to_curve(),
+            SplineKind::Bezier => {
+                // Handles after the last point of a segment are only used to close the loop
+                let last = (count.max(1) - 1) / 3 * 3;
+                let mut segments: Vec<[Vec3; 4]> = points[..count.min(last + 1)]
+                    .windows(4)
+                    .step_by(3)
+                    .map(|window| [window[0], window[1], window[2], window[3]])
+                    .collect();
+                let closed = last > 0 && last + 1 == count && points[last].distance(points[0]) < f32::EPSILON;
+                if cyclic && count > 1 && !closed {
+                    // Missing handles of the closing segment mirror the ones of its neighbours,
+                    // so the loop keeps its tangent through the first and last points
+                    let (end, start) = (points[last], points[0]);
+                    let leaving = points.get(last + 1).copied().unwrap_or_else(|| match last {
+                        0 => end.lerp(start, 1.0 / 3.0),
+                        _ => end * 2.0 - points[last - 1],
+                    });
+                    let entering = points.get(last + 2).copied().unwrap_or(start * 2.0 - points[1]);
+                    segments.push([end, leaving, entering, start]);
+                }
+                CubicBezier::new(segments).to_curve()
+            }
+            SplineKind::Linear => {
+                let points = if cyclic { [points, &points[..count.min(1)]].concat() } else { points.to_vec() };
+                LinearSpline::new(points).to_curve()
+            }
+        }
+    }
+
+    /// Curve parameter at which the curve passes through, or comes closest
+    /// to, the point at `index` out of `count`
+    fn waypoint_progress(&self, index: usize, count: usize) -> f32 {
+        match self {
+            SplineKind::Cardinal { .. } | SplineKind::Linear => index as f32,
+            SplineKind::Bezier => index as f32 / 3.0,
+            // Each knot is drawn toward the point in the middle of its segment
+            SplineKind::BSpline => (index as f32 - 1.0).clamp(0.0, count.saturating_sub(3) as f32),
+        }
+    }
+
+    /// Index of the first point shaping the segment `segment` of the curve
+    fn first_point(&self, segment: usize) -> usize {
+        match self {
+            SplineKind::Bezier => segment * 3,
+            SplineKind::Cardinal { .. } | SplineKind::BSpline | SplineKind::Linear => segment,
+        }
+    }
+}
+
+/// Curve through the path points, with the distance travelled along it
+/// sampled at regular steps of the curve parameter.
+struct ArcLength {
+    curve: CubicCurve<Vec3>,
+    /// Distance from the start of the curve, for each sample
+    lengths: Vec<f32>,
+}
+
+impl ArcLength {
+    fn new(curve: CubicCurve<Vec3>) -> Option<Self> {
+        if curve.segments().is_empty() {
+            return None;
+        }
+        let samples = curve.segments().len() * SAMPLES_PER_SEGMENT;
+
+        let mut lengths = Vec::with_capacity(samples + 1);
+        let mut last = curve.position(0.0);
+        let mut total = 0.0;
+        lengths.push(total);
+        for sample in 1..=samples {
+            let position = curve.position(sample as f32 / SAMPLES_PER_SEGMENT as f32);
+            total += position.distance(last);
+            lengths.push(total);
+            last = position;
+        }
+
+        Some(Self { curve, lengths })
+    }
+
+    /// Curve parameter at the end of the curve
+    fn end(&self) -> f32 {
+        self.curve.segments().len() as f32
+    }
+
+    /// Total length of the curve
+    fn length(&self) -> f32 {
+        self.lengths.last().copied().unwrap_or(0.0)
+    }
+
+    /// Distance travelled along the curve at the curve parameter `progress`
+    fn distance_at(&self, progress: f32) -> f32 {
+        let last = self.lengths.len().saturating_sub(2);
+        let sample = (progress * SAMPLES_PER_SEGMENT as f32).max(0.0);
+        let index = (sample as usize).min(last);
+        match (self.lengths.get(index), self.lengths.get(index + 1)) {
+            (Some(from), Some(to)) => from + (to - from) * (sample - index as f32).min(1.0),
+            _ => 0.0,
+        }
+    }
+
+    /// Curve parameter reached after travelling `distance` along the curve
+    fn progress_at(&self, distance: f32) -> f32 {
+        if self.lengths.len() < 2 {
+            return 0.0;
+        }
+        let index = self
+            .lengths
+            .partition_point(|length| *length <= distance)
+            .clamp(1, self.lengths.len() - 1);
+        let (from, to) = (self.lengths[index - 1], self.lengths[index]);
+        let t = if to > from { ((distance - from) / (to - from)).clamp(0.0, 1.0) } else { 0.0 };
+        (index as f32 - 1.0 + t) / SAMPLES_PER_SEGMENT as f32
+    }
+}
 
 #[derive(Component)]
 pub struct PathComponents{
     points: Vec<Vec3>,
+    /// Position on the curve, each point being one unit further than the previous one
     progress: f32,
+    /// Distance travelled along the curve, matching `progress`
+    distance: f32,
+    /// Travel speed along the path, in world units per second
+    speed: f32,
+    /// Distance over which the creature speeds up at the start of the path
+    /// and slows down before its end. No easing when 0
+    easing: f32,
+    spline: SplineKind,
+    /// The curve goes back to the first point, and the creature loops on it
+    cyclic: bool,
+    /// Cached curve through the points, rebuilt after they change
+    arc_length: Option<ArcLength>,
 }
 
 impl PathComponents {
@@ -41,42 +236,128 @@ impl PathComponents {
         Self {
             points,
             progress: 0.0,
+            distance: 0.0,
+            speed: 100.0,
+            easing: 0.0,
+            spline: SplineKind::default(),
+            cyclic: false,
+            arc_length: None,
+        }
+    }
+
+    pub fn with_speed(mut self, speed: f32) -> Self {
+        self.speed = speed;
+        self
+    }
+
+    pub fn with_easing(mut self, easing: f32) -> Self {
+        self.easing = easing;
+        self
+    }
+
+    pub fn with_spline(mut self, spline: SplineKind) -> Self {
+        self.spline = spline;
+        self
+    }
+
+    /// Follow a path drawn in svg, `scale` and `offset` bring it to the world
+    #[allow(dead_code)]
+    pub fn from_svg(svg: &SvgPath, scale: f32, offset: Vec2) -> Self {
+        Self::new(svg.world_points(scale, offset)).with_spline(SplineKind::Bezier)
+    }
+
+    /// Drop the cached curve, to be called after changing the points
+    fn invalidate(&mut self) {
+        self.arc_length = None;
+    }
+
+    /// Curve through the points, built on first use
+    fn arc_length(&mut self) -> Option<&ArcLength> {
+        if self.arc_length.is_none() && self.points.len() > 1 {
+            self.arc_length = ArcLength::new(self.spline.curve(&self.points, self.cyclic));
+            // Keep the position on the curve, the distance changes with its shape
+            if let Some(arc_length) = &self.arc_length {
+                self.distance = arc_length.distance_at(self.progress);
+            }
         }
+        self.arc_length.as_ref()
     }
 }
 
+/// Points followed over and over by a creature.
+///
+/// An open loop slides along the points, adding the next one to the path
+/// each time one is reached. A closed loop follows a single cyclic curve.
 #[derive(Component)]
 pub struct PathLoop {
     pub points: Vec<Vec3>,
     pub next_idx: usize,
+    pub spline: SplineKind,
+    pub closed: bool,
 }
 
 impl PathLoop {
+    #[allow(dead_code)]
     pub fn new(points: Vec<Vec3>) -> Self {
         Self {
             points,
             next_idx: 0,
+            spline: SplineKind::default(),
+            closed: false,
         }
     }
 
-    pub fn random(count: usize, x_range: Range<f32>, y_range: Range<f32>) -> Self {
+    pub fn with_spline(mut self, spline: SplineKind) -> Self {
+        self.spline = spline;
+        self
+    }
+
+    pub fn closed(mut self) -> Self {
+        self.closed = true;
+        self
+    }
+
+    /// Loop on a path drawn in svg, `scale` and `offset` bring it to the world.
+    ///
+    /// The loop is always closed, an open path goes back to its start
+    /// with a smooth segment.
+    pub fn from_svg(svg: &SvgPath, scale: f32, offset: Vec2) -> Self {
+        Self::new(svg.world_points(scale, offset)).with_spline(SplineKind::Bezier).closed()
+    }
+
+    #[allow(dead_code)]
+    pub fn random(count: usize, x_range: Range<f32>, y_range: Range<f32>, rng: &mut impl Rng) -> Self {
         let mut points = Vec::new();
         for _ in 0..count {
             let vec = Vec3::new(
-                rand::thread_rng().gen_range(x_range.clone()),
-                rand::thread_rng().gen_range(y_range.clone()),
-                rand::thread_rng().gen_range(x_range.clone())
+                rng.gen_range(x_range.clone()),
+                rng.gen_range(y_range.clone()),
+                rng.gen_range(x_range.clone())
             );
             points.push(vec);
         }
 
-        Self {
-            points,
-            next_idx: 0,
+        Self::new(points)
+    }
+
+    /// Remove the first segment of the path, after reaching the third one,
+    /// and add the next points of the loop in its place. The segments in
+    /// between keep their points, so the curve stays smooth under the creature.
+    fn slide(&mut self, path: &mut PathComponents) {
+        let stride = path.spline.first_point(1).min(path.points.len());
+        path.points.drain(0..stride);
+        path.progress -= 1.0;
+
+        // add new target points
+        for _ in 0..stride {
+            path.points.push(self.points[self.next_idx]);
+            self.next_idx = (self.next_idx + 1) % self.points.len();
         }
+        path.invalidate();
     }
 }
 
+#[allow(dead_code)]
 fn setup(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
@@ -97,80 +378,186 @@ fn setup(
         },
         // Path(bezier),
         PathComponents::new(vec![Vec3::ZERO]),
-        PathLoop{points, next_idx: 0},
+        PathLoop::new(points),
     ));
 }
 
 fn follow_path (
-    mut gizmos: Gizmos,
     time: Res<Time>,
     mut query: Query<(&mut KinematicChain, &mut PathComponents), With<Creature>>,
 ) {
     for (mut squeleton, mut path) in &mut query {
+        let path = path.as_mut();
 
-        // Draw the points for reference
-        for point in &path.points {
-            gizmos.circle_2d(point.truncate(), 5.0, COLOR_GREEN);
-        }
+        let Some(length) = path.arc_length().map(ArcLength::length) else {
+            continue;
+        };
 
-        let target: f32 = (path.points.len() -1) as f32;
-        if path.progress < target {
-            path.progress += time.delta_seconds() * 0.4;
+        // Constant speed along the curve, slowed down close to the ends
+        let mut speed = path.speed;
+        if path.easing > 0.0 && !path.cyclic {
+            let from_end = path.distance.min(length - path.distance);
+            speed *= (from_end / path.easing).clamp(MIN_EASED_SPEED, 1.0);
         }
+        path.distance += speed * time.delta_seconds();
 
-        if path.progress > target {
+        if path.cyclic {
+            path.distance = path.distance.rem_euclid(length.max(f32::EPSILON));
+        } else if path.distance >= length {
             // We reached the last point. Clear the path
             path.points.clear();
             path.points.push(squeleton.target);
             path.progress = 0.0;
+            path.distance = 0.0;
+            path.invalidate();
+            continue;
         }
 
-        if path.points.len() > 1 {
+        let Some(arc_length) = path.arc_length.as_ref() else {
+            continue;
+        };
+        path.progress = arc_length.progress_at(path.distance);
 
-            let bezier = CubicCardinalSpline::new(0.5, path.points.clone()).to_curve();
-            gizmos.linestrip(bezier.iter_positions(path.points.len() * 50), COLOR_WHITE);
+        // position takes a point from the curve where 0 is the initial point
+        // and 1 is the last point
+        squeleton.target = arc_length.curve.position(path.progress);
+        // Flat creatures stay in the top-down plane, 3d ones follow the path height
+        squeleton.target = squeleton.space.project(squeleton.target);
+    }
+}
 
-            // This version needs at least 3 points. It is smoother, but doesn't pass
-            // through the control point position.
-            // let bezier = CubicBSpline::new(path.points.clone()).to_curve();
-            // gizmos.linestrip(bezier.iter_positions(path.points.len() * 50), COLOR_RED);
+/// Draw the points and the curve followed by the creatures, for reference
+fn draw_paths (
+    mut gizmos: Gizmos,
+    query: Query<&PathComponents, With<Creature>>,
+) {
+    for path in &query {
+        for point in &path.points {
+            gizmos.circle_2d(point.truncate(), 5.0, COLOR_GREEN);
+        }
 
-            // position takes a point from the curve where 0 is the initial point
-            // and 1 is the last point
-            // transform.translation = bezier.position(path.progress * bezier.segments().len() as f32);
-            squeleton.target = bezier.position(path.progress);
-            // We build a random Vec3, fix the z to 0.0 for now
-            squeleton.target.z = 0.0;
+        if let Some(arc_length) = &path.arc_length {
+            gizmos.linestrip(arc_length.curve.iter_positions(path.points.len() * 50), COLOR_WHITE);
         }
     }
 }
 
+impl PathComponents {
+    /// Index of the first waypoint not reached yet
+    fn first_pending(&self) -> usize {
+        let count = self.points.len();
+        (0..count)
+            .find(|index| self.spline.waypoint_progress(*index, count) > self.progress)
+            .unwrap_or(count)
+    }
+
+    /// Pending waypoint under `position`, if any
+    fn pick(&self, position: Vec2) -> Option<usize> {
+        (self.first_pending()..self.points.len())
+            .find(|i| self.points[*i].truncate().distance(position) < PICK_RADIUS)
+    }
+}
+
+/// Edit the path of the playable creature with the mouse.
+///
+/// Left click adds a waypoint, or grabs an existing one to drag it around.
+/// Shift + left click deletes a waypoint, C clears the whole path.
 fn add_points (
     buttons: Res<ButtonInput<MouseButton>>,
-    mut query: Query<(&Transform, &mut PathComponents)>,
-    mut mycoords: ResMut<MyWorldCoords>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut editor: ResMut<WaypointEditor>,
+    mut query: Query<(&KinematicChain, &mut PathComponents), With<Playable>>,
+    mycoords: Res<MyWorldCoords>,
 ) {
+    let Ok((squeleton, mut path)) = query.get_single_mut() else {
+        return;
+    };
+    let Some(head) = squeleton.nodes.first().map(|node| node.0) else {
+        return;
+    };
+
     if buttons.just_pressed(MouseButton::Left) {
-        let (pos, mut path) = query.single_mut();
+        let picked = path.pick(mycoords.0);
+
+        if keyboard.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+            if let Some(index) = picked {
+                path.points.remove(index);
+                path.invalidate();
+            }
+            return;
+        }
+
+        if picked.is_some() {
+            editor.dragging = picked;
+            return;
+        }
+
         // Add the new final destination
         path.points.push(mycoords.0.extend(0.0));
 
-        // Remove the already reached points and
-        // change the first point to be the current position
-        if path.progress != 0.0 {
-            let progress = path.progress;
-            path.points.drain(0..progress as usize);
-            let first = path.points.first_mut().unwrap();
-            *first = pos.translation;
-            path.progress = 0.0;
+        // Remove the already reached points, keeping the creature
+        // where it is on the current segment
+        let reached = path.progress.floor();
+        let first_point = path.spline.first_point(reached as usize).min(path.points.len());
+        path.points.drain(0..first_point);
+        path.progress -= reached;
+        path.invalidate();
+    }
+
+    if buttons.pressed(MouseButton::Left) {
+        let first_pending = path.first_pending();
+        if let Some(point) = editor
+            .dragging
+            .filter(|index| *index >= first_pending)
+            .and_then(|index| path.points.get_mut(index))
+        {
+            *point = mycoords.0.extend(0.0);
+            path.invalidate();
         }
     }
 
-    if buttons.just_pressed(MouseButton::Right) {
-        let (pos, mut path) = query.single_mut();
+    if buttons.just_released(MouseButton::Left) {
+        editor.dragging = None;
+    }
+
+    if keyboard.just_pressed(KeyCode::KeyC) {
         path.points.clear();
-        path.points.push(pos.translation);
+        path.points.push(head);
         path.progress = 0.0;
+        path.invalidate();
+        editor.dragging = None;
+    }
+}
+
+/// Show the waypoints left to the playable creature, and the curve through them
+fn draw_waypoints (
+    mut gizmos: Gizmos<WaypointGizmos>,
+    editor: Res<WaypointEditor>,
+    mycoords: Res<MyWorldCoords>,
+    mut query: Query<&mut PathComponents, With<Playable>>,
+) {
+    for mut path in &mut query {
+        let hovered = editor.dragging.or_else(|| path.pick(mycoords.0));
+        for index in path.first_pending()..path.points.len() {
+            let point = path.points[index].truncate();
+            if Some(index) == hovered {
+                gizmos.circle_2d(point, PICK_RADIUS, COLOR_RED);
+            } else {
+                gizmos.circle_2d(point, 5.0, COLOR_WHITE);
+            }
+        }
+
+        // Remaining part of the curve followed by the creature,
+        // built here when the waypoints were just edited
+        let progress = path.progress;
+        if let Some(arc_length) = path.arc_length() {
+            let curve = &arc_length.curve;
+            let end = arc_length.end();
+            let steps = ((end - progress) * 20.0).max(1.0) as usize;
+            let positions = (0..=steps)
+                .map(|step| curve.position(progress + (end - progress) * step as f32 / steps as f32));
+            gizmos.linestrip(positions, COLOR_LIGHT_BLUE);
+        }
     }
 }
 
@@ -178,22 +565,62 @@ fn loop_path (
     mut query: Query<(&mut PathLoop, &mut PathComponents)>,
 ) {
     for (mut path_loop, mut path) in &mut query {
+        if path.cyclic {
+            // The creature goes round the closed curve on its own
+            continue;
+        }
+
         if path.progress == 0.0 {
             // Initiate the path the the loop points
             path.points = path_loop.points.clone();
+            path.spline = path_loop.spline;
+            path.cyclic = path_loop.closed;
+            path.invalidate();
         } else if path.progress > 2.0 {
-            // Remove the first point, after reaching the second,
-            // in order to keep the curve smooth.
-            path.points.drain(0..1);
-            path.progress = 1.0;
+            path_loop.slide(&mut path);
+        }
+    }
+}
 
-            // add new target point
-            path.points.push(path_loop.points[path_loop.next_idx]);
+/// Loop on a path drawn in an svg file, set up once the file is loaded
+#[derive(Component)]
+pub struct SvgRoute {
+    pub path: Handle<SvgPath>,
+    pub scale: f32,
+    pub offset: Vec2,
+}
 
-            if path_loop.next_idx < path_loop.points.len()-1 {
-                path_loop.next_idx += 1;
-            } else {
-                path_loop.next_idx = 0;
+/// Give their loop to the creatures following an svg route, every time the
+/// file is loaded or modified.
+fn load_svg_routes (
+    mut commands: Commands,
+    mut events: EventReader<AssetEvent<SvgPath>>,
+    svg_paths: Res<Assets<SvgPath>>,
+    mut query: Query<(Entity, &SvgRoute, Option<&mut PathLoop>, Option<&mut PathComponents>)>,
+) {
+    for event in events.read() {
+        let (AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id }) = event else {
+            continue;
+        };
+        let Some(svg) = svg_paths.get(*id) else {
+            continue;
+        };
+
+        for (entity, route, path_loop, path) in query.iter_mut().filter(|(_, route, ..)| route.path.id() == *id) {
+            let new_loop = PathLoop::from_svg(svg, route.scale, route.offset);
+            match path_loop {
+                Some(mut path_loop) => *path_loop = new_loop,
+                None => {
+                    commands.entity(entity).insert(new_loop);
+                }
+            }
+
+            // Wait where it is for the new loop to start
+            if let Some(mut path) = path {
+                path.points.truncate(1);
+                path.progress = 0.0;
+                path.cyclic = false;
+                path.invalidate();
             }
         }
     }
@@ -227,4 +654,119 @@ fn loop_path (
     //     },
     //     Stroke::new(COLOR_BLACK, 10.0),
     //     Fill::color(COLOR_RED),
-    // ));
\ No newline at end of file
+    // ));
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path_at(spline: SplineKind, count: usize, progress: f32) -> PathComponents {
+        let mut path = PathComponents::new(vec![Vec3::ZERO; count]).with_spline(spline);
+        path.progress = progress;
+        path
+    }
+
+    #[test]
+    fn pending_waypoints_follow_the_spline_kind() {
+        // Every point is on a cardinal curve
+        assert_eq!(path_at(SplineKind::default(), 5, 0.0).first_pending(), 1);
+        assert_eq!(path_at(SplineKind::default(), 5, 1.5).first_pending(), 2);
+        assert_eq!(path_at(SplineKind::Linear, 5, 3.0).first_pending(), 4);
+
+        // Three points per segment of a bezier curve
+        assert_eq!(path_at(SplineKind::Bezier, 7, 0.5).first_pending(), 2);
+        assert_eq!(path_at(SplineKind::Bezier, 7, 1.2).first_pending(), 4);
+        assert_eq!(path_at(SplineKind::Bezier, 7, 2.0).first_pending(), 7);
+
+        // The b-spline is drawn toward the point after the start of the segment
+        assert_eq!(path_at(SplineKind::BSpline, 6, 0.0).first_pending(), 2);
+        assert_eq!(path_at(SplineKind::BSpline, 6, 1.5).first_pending(), 3);
+        assert_eq!(path_at(SplineKind::BSpline, 6, 3.0).first_pending(), 6);
+    }
+
+    fn square() -> Vec<Vec3> {
+        vec![
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(40.0, -10.0, 0.0),
+            Vec3::new(90.0, 10.0, 0.0),
+            Vec3::new(100.0, 0.0, 0.0),
+            Vec3::new(110.0, 50.0, 0.0),
+            Vec3::new(100.0, 90.0, 0.0),
+            Vec3::new(100.0, 100.0, 0.0),
+            Vec3::new(50.0, 120.0, 0.0),
+            Vec3::new(-10.0, 110.0, 0.0),
+            Vec3::new(0.0, 100.0, 0.0),
+        ]
+    }
+
+    /// The loop ends where it starts, heading the same way
+    fn assert_loops(curve: &CubicCurve<Vec3>) {
+        let end = curve.segments().len() as f32;
+        assert!(curve.position(0.0).distance(curve.position(end)) < 1e-3);
+        assert!(curve.velocity(0.0).distance(curve.velocity(end)) < 1e-2);
+    }
+
+    #[test]
+    fn cyclic_curves_are_smooth_where_they_close() {
+        for spline in [SplineKind::default(), SplineKind::BSpline, SplineKind::Bezier] {
+            assert_loops(&spline.curve(&square(), true));
+        }
+    }
+
+    #[test]
+    fn cyclic_bezier_mirrors_the_missing_handles() {
+        let points = square();
+        let curve = SplineKind::Bezier.curve(&points, true);
+        assert_eq!(curve.segments().len(), 4);
+
+        // The tangent doesn't break on the last point either
+        let joint = 3.0;
+        assert!(curve.velocity(joint - 1e-4).distance(curve.velocity(joint + 1e-4)) < 1e-1);
+
+        // One handle given, the other one mirrored
+        let curve = SplineKind::Bezier.curve(&[&points[..], &[Vec3::new(-10.0, 80.0, 0.0)]].concat(), true);
+        assert_eq!(curve.segments().len(), 4);
+        assert_loops(&curve);
+    }
+
+    #[test]
+    fn cyclic_bezier_keeps_its_closing_handles() {
+        // Closing segment drawn by hand, back to the first point
+        let mut points = square();
+        points.extend([Vec3::new(-10.0, 80.0, 0.0), Vec3::new(-10.0, 20.0, 0.0)]);
+        let curve = SplineKind::Bezier.curve(&points, true);
+        assert_eq!(curve.segments().len(), 4);
+        let middle = (points[9] + (points[10] + points[11]) * 3.0 + points[0]) / 8.0;
+        assert!(curve.position(3.5).distance(middle) < 1e-3);
+        assert!(curve.position(4.0).distance(points[0]) < 1e-3);
+
+        // Already ending on the first point, nothing to add
+        points.push(points[0]);
+        let curve = SplineKind::Bezier.curve(&points, true);
+        assert_eq!(curve.segments().len(), 4);
+    }
+
+    #[test]
+    fn sliding_loops_keep_the_creature_in_place() {
+        let splines = [
+            SplineKind::default(),
+            SplineKind::Linear,
+            SplineKind::Bezier,
+            SplineKind::BSpline,
+        ];
+        for spline in splines {
+            let mut path_loop = PathLoop::new(square()).with_spline(spline);
+            let mut path = PathComponents::new(square()).with_spline(spline);
+            path.progress = 2.25;
+            let before = path.arc_length().unwrap().curve.position(2.25);
+
+            path_loop.slide(&mut path);
+
+            let stride = spline.first_point(1);
+            assert_eq!(path.points.len(), square().len(), "{spline:?}");
+            assert_eq!(path.points[square().len() - stride..], square()[..stride]);
+            assert_eq!(path.progress, 1.25);
+            let after = path.arc_length().unwrap().curve.position(1.25);
+            assert!(before.distance(after) < 1e-3, "{spline:?} jumped from {before} to {after}");
+        }
+    }
+}
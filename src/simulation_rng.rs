@@ -0,0 +1,208 @@
+/* SPDX-License-Identifier: MIT
+* Copyright (c) 2024 Louis Mayencourt
+*/
+
+use bevy::prelude::*;
+use rand::{rngs::StdRng, RngCore, SeedableRng};
+
+use crate::creatures::Creature;
+
+/// Name of the command line argument, url query parameter and environment
+/// variable giving the seed of the simulation
+const SEED_PARAMETER: &str = "seed";
+
+pub struct SimulationRngPlugin;
+
+impl Plugin for SimulationRngPlugin {
+    fn build(&self, app: &mut App) {
+        let seed = configured_seed().unwrap_or_else(rand::random);
+        info!("Simulation seed: {seed}, replay it with --{SEED_PARAMETER} {seed}");
+
+        app.insert_resource(SimulationRng::new(seed));
+        app.add_systems(PreUpdate, seed_entity_rngs);
+    }
+}
+
+/// Random numbers of the whole simulation.
+///
+/// Everything procedural draws from it, so the same seed builds the same
+/// aquarium again. Creatures get their own stream derived from it, to keep
+/// the other creatures unaffected when one of them draws more numbers.
+#[derive(Resource)]
+pub struct SimulationRng {
+    rng: StdRng,
+}
+
+impl SimulationRng {
+    pub fn new(seed: u64) -> Self {
+        SimulationRng {
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    /// New stream, derived from the next numbers of the simulation
+    pub fn fork(&mut self) -> EntityRng {
+        EntityRng(StdRng::seed_from_u64(self.rng.next_u64()))
+    }
+}
+
+impl RngCore for SimulationRng {
+    fn next_u32(&mut self) -> u32 {
+        self.rng.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.rng.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.rng.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.rng.try_fill_bytes(dest)
+    }
+}
+
+/// Random numbers of a single creature
+#[derive(Component)]
+pub struct EntityRng(StdRng);
+
+impl RngCore for EntityRng {
+    fn next_u32(&mut self) -> u32 {
+        self.0.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.0.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.0.try_fill_bytes(dest)
+    }
+}
+
+/// Give their own stream to the new creatures, in the order they were spawned.
+///
+/// The creatures are sorted by entity, which follows the spawn order as long
+/// as no entity was despawned before: a despawned entity leaves its index to
+/// the next ones spawned. All the creatures of the aquarium are spawned at
+/// startup, the ones spawned later only get a reproducible stream if the same
+/// entities were despawned before them.
+pub fn seed_entity_rngs(
+    mut commands: Commands,
+    mut rng: ResMut<SimulationRng>,
+    creatures: Query<Entity, (With<Creature>, Without<EntityRng>)>,
+) {
+    // The query goes through the creatures grouped by their components, sort
+    // them so a creature gets the same stream whatever else it is made of
+    let mut entities: Vec<Entity> = creatures.iter().collect();
+    entities.sort_unstable();
+    for entity in entities {
+        commands.entity(entity).insert(rng.fork());
+    }
+}
+
+/// Seed given on the command line (`--seed 42`), in the url of the page
+/// (`?seed=42`) or in the `SEED` environment variable, the first one which
+/// is a number
+fn configured_seed() -> Option<u64> {
+    let mut args = std::env::args().skip_while(|arg| *arg != format!("--{SEED_PARAMETER}"));
+    let seeds = [
+        args.nth(1),
+        url_seed(),
+        std::env::var(SEED_PARAMETER.to_uppercase()).ok(),
+    ];
+    seeds.into_iter().flatten().find_map(|seed| match seed.trim().parse() {
+        Ok(seed) => Some(seed),
+        Err(_) => {
+            warn!("Ignoring the seed {seed:?}, it is not a positive integer");
+            None
+        }
+    })
+}
+
+#[cfg(target_arch = "wasm32")]
+fn url_seed() -> Option<String> {
+    let search = web_sys::window()?.location().search().ok()?;
+    search
+        .trim_start_matches('?')
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| *key == SEED_PARAMETER)
+        .map(|(_, value)| value.to_string())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn url_seed() -> Option<String> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::creatures::kinematic_chain::KinematicChain;
+    use crate::creatures::skin_mesh::SkinMesh;
+    use crate::ecosystem::Diet;
+    use crate::test_support::{aquarium, run_steps, spawn_wanderer, STEP};
+
+    /// Head to tail positions and color of every creature, in spawn order
+    fn snapshot(app: &mut App) -> Vec<(Vec<Vec3>, Color)> {
+        let mut creatures = app
+            .world_mut()
+            .query::<(Entity, &KinematicChain, &SkinMesh)>()
+            .iter(app.world())
+            .map(|(entity, chain, skin)| {
+                (entity, chain.nodes.iter().map(|node| node.0).collect(), skin.color)
+            })
+            .collect::<Vec<(Entity, Vec<Vec3>, Color)>>();
+        creatures.sort_by_key(|(entity, ..)| *entity);
+        creatures.into_iter().map(|(_, nodes, color)| (nodes, color)).collect()
+    }
+
+    /// Six wandering fish, after 60 simulation steps
+    fn run(seed: u64) -> Vec<(Vec<Vec3>, Color)> {
+        let mut app = aquarium(seed, STEP);
+        for i in 0..6 {
+            let fish = spawn_wanderer(&mut app, include_str!("../assets/species/fish.species.ron"));
+            // Mix the components, so the creatures are not queried in spawn order
+            if i % 2 == 1 {
+                app.world_mut().entity_mut(fish).insert(Diet::Omnivore);
+            }
+        }
+        run_steps(&mut app, 60);
+        snapshot(&mut app)
+    }
+
+    #[test]
+    fn same_seed_builds_the_same_aquarium() {
+        let aquarium = run(7);
+        assert_eq!(aquarium.len(), 6);
+        assert_eq!(aquarium, run(7));
+        assert_ne!(aquarium, run(8));
+    }
+
+    #[test]
+    fn streams_follow_the_spawn_order() {
+        let mut app = App::new();
+        app.insert_resource(SimulationRng::new(7))
+            .add_systems(Update, seed_entity_rngs);
+        // The second creature is queried last, as it is the only one without a diet
+        let creatures = [
+            app.world_mut().spawn((Creature, Diet::Herbivore)).id(),
+            app.world_mut().spawn(Creature).id(),
+            app.world_mut().spawn((Creature, Diet::Carnivore)).id(),
+        ];
+        app.update();
+
+        let mut expected = SimulationRng::new(7);
+        for creature in creatures {
+            let mut rng = app.world_mut().get_mut::<EntityRng>(creature).unwrap();
+            assert_eq!(rng.next_u64(), expected.fork().next_u64());
+        }
+    }
+}
@@ -0,0 +1,247 @@
+/* SPDX-License-Identifier: MIT
+* Copyright (c) 2024 Louis Mayencourt
+*/
+
+//! Steering behaviours, moving the head of the creatures with some momentum.
+//!
+//! Each behaviour gives the velocity the creature would like to have. The
+//! velocities are blended by weight, then the creature turns and accelerates
+//! toward it within its limits. The head target is moved along the velocity.
+
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use rand::prelude::*;
+
+use crate::creatures::{kinematic_chain::KinematicChain, SimulationSet};
+use crate::simulation_rng::EntityRng;
+
+pub struct SteeringPlugin;
+
+impl Plugin for SteeringPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(FixedUpdate, steer.in_set(SimulationSet::Target));
+    }
+}
+
+/// Round obstacle the creatures swim around
+#[derive(Component)]
+pub struct Obstacle {
+    pub radius: f32,
+}
+
+/// Intention of a creature, giving the velocity it would like to have
+#[allow(dead_code)]
+#[derive(Clone, Copy, Debug)]
+pub enum Behaviour {
+    /// Go to a point at full speed
+    Seek(Vec3),
+    /// Run away from a point
+    Flee(Vec3),
+    /// Go to a point, slowing down within `slowing_radius` to stop on it
+    Arrive { target: Vec3, slowing_radius: f32 },
+    /// Stroll around, turning by at most `jitter` degrees per second
+    Wander { distance: f32, radius: f32, jitter: f32 },
+    /// Intercept another creature, where it will be
+    Pursue(Entity),
+    /// Run away from another creature, when it comes closer than `range`
+    Evade { entity: Entity, range: f32 },
+    /// Swim around the obstacles up to `look_ahead` in front
+    AvoidObstacles { look_ahead: f32 },
+    /// Stay within a rectangle
+    Contain(Rect),
+}
+
+/// Head of a creature moved by weighted steering behaviours
+#[derive(Component)]
+pub struct Steering {
+    pub behaviours: Vec<(Behaviour, f32)>,
+    pub velocity: Vec3,
+    pub max_speed: f32,
+    /// Maximum change of velocity per second
+    pub max_force: f32,
+    /// Maximum change of heading, in degrees per second
+    pub max_turn_rate: f32,
+    wander_angle: f32,
+}
+
+impl Steering {
+    pub fn new(max_speed: f32, max_force: f32, max_turn_rate: f32) -> Self {
+        Steering {
+            behaviours: Vec::new(),
+            velocity: Vec3::ZERO,
+            max_speed,
+            max_force,
+            max_turn_rate,
+            wander_angle: 0.0,
+        }
+    }
+
+    pub fn with(mut self, behaviour: Behaviour, weight: f32) -> Self {
+        self.behaviours.push((behaviour, weight));
+        self
+    }
+}
+
+pub fn seek(position: Vec3, target: Vec3, max_speed: f32) -> Vec3 {
+    (target - position).normalize_or_zero() * max_speed
+}
+
+pub fn flee(position: Vec3, target: Vec3, max_speed: f32) -> Vec3 {
+    -seek(position, target, max_speed)
+}
+
+pub fn arrive(position: Vec3, target: Vec3, slowing_radius: f32, max_speed: f32) -> Vec3 {
+    let distance = position.distance(target);
+    let speed = max_speed * (distance / slowing_radius.max(f32::EPSILON)).min(1.0);
+    seek(position, target, speed)
+}
+
+/// Point ahead of the creature on a circle, at `angle` degrees from its heading
+pub fn wander(position: Vec3, velocity: Vec3, distance: f32, radius: f32, angle: f32, max_speed: f32) -> Vec3 {
+    let heading = velocity.truncate().normalize_or(Vec2::X);
+    let center = position.truncate() + heading * distance;
+    let point = center + Vec2::from_angle(angle.to_radians()).rotate(heading) * radius;
+    seek(position, point.extend(position.z), max_speed)
+}
+
+/// Where the creature at `target` will be when reached
+fn predict(position: Vec3, target: Vec3, target_velocity: Vec3, max_speed: f32) -> Vec3 {
+    let time = position.distance(target) / max_speed.max(f32::EPSILON);
+    target + target_velocity * time
+}
+
+pub fn pursue(position: Vec3, target: Vec3, target_velocity: Vec3, max_speed: f32) -> Vec3 {
+    seek(position, predict(position, target, target_velocity, max_speed), max_speed)
+}
+
+pub fn evade(position: Vec3, target: Vec3, target_velocity: Vec3, max_speed: f32) -> Vec3 {
+    flee(position, predict(position, target, target_velocity, max_speed), max_speed)
+}
+
+/// Sidestep the closest obstacle in front, if any
+pub fn avoid_obstacles(
+    position: Vec3,
+    velocity: Vec3,
+    obstacles: &[(Vec3, f32)],
+    look_ahead: f32,
+    max_speed: f32,
+) -> Option<Vec3> {
+    let heading = velocity.truncate().normalize_or_zero();
+    if heading == Vec2::ZERO {
+        return None;
+    }
+
+    obstacles
+        .iter()
+        .filter_map(|(center, radius)| {
+            let offset = (*center - position).truncate();
+            let ahead = offset.dot(heading);
+            let side = offset.dot(heading.perp());
+            let blocking = ahead > 0.0 && ahead < look_ahead + radius && side.abs() < *radius;
+            blocking.then_some((ahead, side))
+        })
+        .min_by(|a, b| a.0.total_cmp(&b.0))
+        .map(|(_, side)| {
+            // Turn to the side opposite to the obstacle center
+            let away = if side > 0.0 { -heading.perp() } else { heading.perp() };
+            away.extend(0.0) * max_speed
+        })
+}
+
+/// Come back toward the center, once outside of `bounds`
+pub fn contain(position: Vec3, bounds: Rect, max_speed: f32) -> Option<Vec3> {
+    (!bounds.contains(position.truncate()))
+        .then(|| seek(position, bounds.center().extend(position.z), max_speed))
+}
+
+/// Rotate `from` toward `to` by at most `max_angle` radians, with the length of `to`
+fn limit_turn(from: Vec3, to: Vec3, max_angle: f32) -> Vec3 {
+    let (from_2d, to_2d) = (from.truncate(), to.truncate());
+    if from_2d == Vec2::ZERO || to_2d == Vec2::ZERO {
+        return to;
+    }
+
+    let angle = from_2d.angle_between(to_2d);
+    let turn = angle.clamp(-max_angle, max_angle);
+    (Vec2::from_angle(turn).rotate(from_2d.normalize()) * to_2d.length()).extend(to.z)
+}
+
+pub fn steer(
+    time: Res<Time>,
+    obstacles: Query<(&GlobalTransform, &Obstacle)>,
+    mut query: Query<(Entity, &mut KinematicChain, &mut Steering, &mut EntityRng)>,
+) {
+    let delta = time.delta_seconds();
+    if delta <= 0.0 {
+        return;
+    }
+
+    let obstacles: Vec<(Vec3, f32)> = obstacles
+        .iter()
+        .map(|(transform, obstacle)| (transform.translation(), obstacle.radius))
+        .collect();
+    // Position and velocity of the creatures, for the pursuits
+    let creatures: HashMap<Entity, (Vec3, Vec3)> = query
+        .iter()
+        .filter_map(|(entity, chain, steering, _)| {
+            chain.nodes.first().map(|head| (entity, (head.0, steering.velocity)))
+        })
+        .collect();
+
+    for (entity, mut chain, mut steering, mut rng) in query.iter_mut() {
+        let Some(&(position, velocity)) = creatures.get(&entity) else {
+            continue;
+        };
+        let max_speed = steering.max_speed;
+
+        let jitter = steering.behaviours.iter().find_map(|(behaviour, _)| match behaviour {
+            Behaviour::Wander { jitter, .. } => Some(*jitter),
+            _ => None,
+        });
+        if let Some(jitter) = jitter {
+            steering.wander_angle += rng.gen_range(-1.0..1.0) * jitter * delta;
+        }
+
+        let mut force = Vec3::ZERO;
+        for (behaviour, weight) in steering.behaviours.iter() {
+            let desired = match *behaviour {
+                Behaviour::Seek(target) => Some(seek(position, target, max_speed)),
+                Behaviour::Flee(target) => Some(flee(position, target, max_speed)),
+                Behaviour::Arrive { target, slowing_radius } => {
+                    Some(arrive(position, target, slowing_radius, max_speed))
+                }
+                Behaviour::Wander { distance, radius, .. } => Some(wander(
+                    position,
+                    velocity,
+                    distance,
+                    radius,
+                    steering.wander_angle,
+                    max_speed,
+                )),
+                Behaviour::Pursue(other) => creatures
+                    .get(&other)
+                    .map(|(target, target_velocity)| pursue(position, *target, *target_velocity, max_speed)),
+                Behaviour::Evade { entity: other, range } => creatures
+                    .get(&other)
+                    .filter(|(target, _)| target.distance(position) < range)
+                    .map(|(target, target_velocity)| evade(position, *target, *target_velocity, max_speed)),
+                Behaviour::AvoidObstacles { look_ahead } => {
+                    avoid_obstacles(position, velocity, &obstacles, look_ahead, max_speed)
+                }
+                Behaviour::Contain(bounds) => contain(position, bounds, max_speed),
+            };
+
+            if let Some(desired) = desired {
+                force += (chain.space.project(desired) - velocity) * *weight;
+            }
+        }
+
+        let force = force.clamp_length_max(steering.max_force);
+        let turn = steering.max_turn_rate.to_radians() * delta;
+        let velocity = limit_turn(velocity, velocity + force * delta, turn).clamp_length_max(max_speed);
+
+        steering.velocity = velocity;
+        chain.target = position + velocity * delta;
+    }
+}
@@ -0,0 +1,381 @@
+/* SPDX-License-Identifier: MIT
+* Copyright (c) 2024 Louis Mayencourt
+*/
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+};
+use thiserror::Error;
+
+/// Path drawn in a vector tool, from the `d` attribute of an svg `<path>`.
+///
+/// The path is stored as a chain of cubic Bézier segments: the first point,
+/// then two handles and the end point for each segment. Supports the M, L,
+/// H, V, C, Q and Z commands, absolute and relative. A move in the middle of
+/// the path is joined to the previous point with a straight line.
+#[derive(Asset, TypePath, Debug, Clone, Default)]
+pub struct SvgPath {
+    pub points: Vec<Vec2>,
+    /// The path ends with a Z, going back to its first point
+    pub closed: bool,
+}
+
+#[derive(Debug, Error)]
+pub enum SvgPathError {
+    #[error("Could not read the svg file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("The svg file is not valid utf-8: {0}")]
+    Utf8(#[from] std::string::FromUtf8Error),
+    #[error("No <path> with a d attribute in the svg file")]
+    MissingPath,
+    #[error("Path command {0} is not supported")]
+    UnsupportedCommand(char),
+    #[error("Missing coordinates for the path command {0}")]
+    MissingNumber(char),
+    #[error("Invalid number {0} in the path")]
+    InvalidNumber(String),
+    #[error("The path doesn't start with a move")]
+    MissingMove,
+}
+
+impl SvgPath {
+    /// Parse the path data of the `d` attribute
+    pub fn parse(data: &str) -> Result<Self, SvgPathError> {
+        let mut tokens = Tokens::new(data);
+        let mut path = SvgPath::default();
+        let mut current = Vec2::ZERO;
+        let mut start = Vec2::ZERO;
+
+        while let Some(command) = tokens.command()? {
+            let relative = command.is_ascii_lowercase();
+            let origin = |current: Vec2| if relative { current } else { Vec2::ZERO };
+
+            if path.points.is_empty() && !matches!(command, 'M' | 'm') {
+                return Err(SvgPathError::MissingMove);
+            }
+
+            match command.to_ascii_uppercase() {
+                'M' => {
+                    current = origin(current) + tokens.point(command)?;
+                    if path.points.is_empty() {
+                        path.points.push(current);
+                    } else {
+                        path.line_to(current);
+                    }
+                    start = current;
+                    // Following coordinates are implicit lines
+                    while tokens.has_number() {
+                        current = origin(current) + tokens.point(command)?;
+                        path.line_to(current);
+                    }
+                }
+                'L' => loop {
+                    current = origin(current) + tokens.point(command)?;
+                    path.line_to(current);
+                    if !tokens.has_number() {
+                        break;
+                    }
+                },
+                'H' => loop {
+                    current.x = origin(current).x + tokens.number(command)?;
+                    path.line_to(current);
+                    if !tokens.has_number() {
+                        break;
+                    }
+                },
+                'V' => loop {
+                    current.y = origin(current).y + tokens.number(command)?;
+                    path.line_to(current);
+                    if !tokens.has_number() {
+                        break;
+                    }
+                },
+                'C' => loop {
+                    let base = origin(current);
+                    let first = base + tokens.point(command)?;
+                    let second = base + tokens.point(command)?;
+                    current = base + tokens.point(command)?;
+                    path.points.extend([first, second, current]);
+                    if !tokens.has_number() {
+                        break;
+                    }
+                },
+                'Q' => loop {
+                    let base = origin(current);
+                    let control = base + tokens.point(command)?;
+                    let end = base + tokens.point(command)?;
+                    // Same curve, with the handles of a cubic segment
+                    path.points.extend([
+                        current.lerp(control, 2.0 / 3.0),
+                        end.lerp(control, 2.0 / 3.0),
+                        end,
+                    ]);
+                    current = end;
+                    if !tokens.has_number() {
+                        break;
+                    }
+                },
+                'Z' => {
+                    if current.distance(start) > f32::EPSILON {
+                        path.line_to(start);
+                    }
+                    current = start;
+                    path.closed = true;
+                }
+                _ => return Err(SvgPathError::UnsupportedCommand(command)),
+            }
+        }
+
+        Ok(path)
+    }
+
+    /// Parse the first `<path>` of an svg file. Its transforms are ignored
+    pub fn from_svg(svg: &str) -> Result<Self, SvgPathError> {
+        let data = svg
+            .match_indices("<path")
+            .filter_map(|(index, _)| {
+                let tag = &svg[index..];
+                let tag = &tag[..tag.find('>').unwrap_or(tag.len())];
+                attribute(tag, "d")
+            })
+            .next()
+            .ok_or(SvgPathError::MissingPath)?;
+        Self::parse(data)
+    }
+
+    /// Points of the path in the world, the y axis of svg going down
+    pub fn world_points(&self, scale: f32, offset: Vec2) -> Vec<Vec3> {
+        self.points
+            .iter()
+            .map(|point| (Vec2::new(point.x, -point.y) * scale + offset).extend(0.0))
+            .collect()
+    }
+
+    /// Straight segment to `end`, as a Bézier segment
+    fn line_to(&mut self, end: Vec2) {
+        let from = self.points.last().copied().unwrap_or(end);
+        self.points.extend([from.lerp(end, 1.0 / 3.0), from.lerp(end, 2.0 / 3.0), end]);
+    }
+}
+
+/// Value of the attribute `name` in an xml tag
+fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let mut rest = tag;
+    while let Some(index) = rest.find(name) {
+        let before = rest[..index].chars().last();
+        let after = rest[index + name.len()..].trim_start();
+        rest = &rest[index + name.len()..];
+
+        if !before.is_some_and(char::is_whitespace) {
+            continue;
+        }
+        let Some(value) = after.strip_prefix('=').map(str::trim_start) else {
+            continue;
+        };
+        let quote = value.chars().next()?;
+        if quote != '"' && quote != '\'' {
+            continue;
+        }
+        let value = &value[1..];
+        return value.find(quote).map(|end| &value[..end]);
+    }
+    None
+}
+
+/// Commands and numbers of the path data
+struct Tokens<'a> {
+    data: &'a str,
+}
+
+impl<'a> Tokens<'a> {
+    fn new(data: &'a str) -> Self {
+        Tokens { data }
+    }
+
+    fn skip_separators(&mut self) {
+        self.data = self.data.trim_start_matches(|c: char| c.is_whitespace() || c == ',');
+    }
+
+    fn command(&mut self) -> Result<Option<char>, SvgPathError> {
+        self.skip_separators();
+        let Some(command) = self.data.chars().next() else {
+            return Ok(None);
+        };
+        if !command.is_ascii_alphabetic() {
+            return Err(SvgPathError::InvalidNumber(command.to_string()));
+        }
+        self.data = &self.data[command.len_utf8()..];
+        Ok(Some(command))
+    }
+
+    fn has_number(&mut self) -> bool {
+        self.skip_separators();
+        self.data
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_digit() || matches!(c, '-' | '+' | '.'))
+    }
+
+    fn number(&mut self, command: char) -> Result<f32, SvgPathError> {
+        if !self.has_number() {
+            return Err(SvgPathError::MissingNumber(command));
+        }
+
+        // A sign or a second dot starts the next number, like in "10-5" or ".5.5"
+        let bytes = self.data.as_bytes();
+        let mut end = 0;
+        let mut dot = false;
+        let mut exponent = false;
+        while end < bytes.len() {
+            match bytes[end] {
+                b'-' | b'+' if end == 0 || matches!(bytes[end - 1], b'e' | b'E') => {}
+                b'0'..=b'9' => {}
+                b'.' if !dot && !exponent => dot = true,
+                b'e' | b'E' if !exponent => exponent = true,
+                _ => break,
+            }
+            end += 1;
+        }
+
+        let (number, rest) = self.data.split_at(end);
+        self.data = rest;
+        number
+            .parse()
+            .map_err(|_| SvgPathError::InvalidNumber(number.to_string()))
+    }
+
+    fn point(&mut self, command: char) -> Result<Vec2, SvgPathError> {
+        Ok(Vec2::new(self.number(command)?, self.number(command)?))
+    }
+}
+
+#[derive(Default)]
+pub struct SvgPathLoader;
+
+impl AssetLoader for SvgPathLoader {
+    type Asset = SvgPath;
+    type Settings = ();
+    type Error = SvgPathError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        SvgPath::from_svg(&String::from_utf8(bytes)?)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["svg"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// End points of the segments, without the handles
+    fn ends(path: &SvgPath) -> Vec<Vec2> {
+        path.points.iter().step_by(3).copied().collect()
+    }
+
+    #[test]
+    fn absolute_and_relative_commands() {
+        let absolute = SvgPath::parse("M 10 10 L 20 10 H 30 V 40 C 30 50 20 50 20 40").unwrap();
+        let relative = SvgPath::parse("m 10 10 l 10 0 h 10 v 30 c 0 10 -10 10 -10 0").unwrap();
+
+        assert_eq!(absolute.points, relative.points);
+        assert_eq!(
+            ends(&absolute),
+            [
+                Vec2::new(10.0, 10.0),
+                Vec2::new(20.0, 10.0),
+                Vec2::new(30.0, 10.0),
+                Vec2::new(30.0, 40.0),
+                Vec2::new(20.0, 40.0),
+            ]
+        );
+        assert_eq!(absolute.points[10], Vec2::new(30.0, 50.0));
+        assert!(!absolute.closed);
+    }
+
+    #[test]
+    fn implicit_repeated_coordinates() {
+        // Extra pairs after a move are lines, after a line more lines
+        let path = SvgPath::parse("M0,0 10,0 l0-10-10,0").unwrap();
+        assert_eq!(
+            ends(&path),
+            [
+                Vec2::new(0.0, 0.0),
+                Vec2::new(10.0, 0.0),
+                Vec2::new(10.0, -10.0),
+                Vec2::new(0.0, -10.0),
+            ]
+        );
+
+        let path = SvgPath::parse("M0 0C1 1 2 2 3 3 4 4 5 5 6 6").unwrap();
+        assert_eq!(path.points.len(), 7);
+        assert_eq!(path.points[6], Vec2::new(6.0, 6.0));
+
+        // Numbers packed without separators
+        let path = SvgPath::parse("M.5.5L1e1-2").unwrap();
+        assert_eq!(ends(&path), [Vec2::new(0.5, 0.5), Vec2::new(10.0, -2.0)]);
+    }
+
+    #[test]
+    fn z_goes_back_to_the_start() {
+        let path = SvgPath::parse("M 0 0 L 10 0 L 10 10 Z").unwrap();
+        assert!(path.closed);
+        assert_eq!(path.points.last(), Some(&Vec2::ZERO));
+        assert_eq!(ends(&path).len(), 4);
+
+        // Already at the start, no extra segment
+        let path = SvgPath::parse("M 0 0 L 10 0 L 0 0 z").unwrap();
+        assert!(path.closed);
+        assert_eq!(ends(&path).len(), 3);
+    }
+
+    #[test]
+    fn malformed_input_is_an_error() {
+        assert!(matches!(
+            SvgPath::parse("L 10 10"),
+            Err(SvgPathError::MissingMove)
+        ));
+        assert!(matches!(
+            SvgPath::parse("M 0 0 L 10"),
+            Err(SvgPathError::MissingNumber('L'))
+        ));
+        assert!(matches!(
+            SvgPath::parse("M 0 0 C 1 1 2 2"),
+            Err(SvgPathError::MissingNumber('C'))
+        ));
+        assert!(matches!(
+            SvgPath::parse("M 0 0 A 5 5 0 0 1 10 10"),
+            Err(SvgPathError::UnsupportedCommand('A'))
+        ));
+        assert!(matches!(
+            SvgPath::parse("M 0 0 L 10 10 #"),
+            Err(SvgPathError::InvalidNumber(_))
+        ));
+        assert!(matches!(
+            SvgPath::parse("M 0 0 L --1 2"),
+            Err(SvgPathError::InvalidNumber(_))
+        ));
+    }
+
+    #[test]
+    fn path_from_svg_file() {
+        let svg = r#"<svg><rect d="M 5 5"/><path id="route" d="M 1 2 L 3 4"/></svg>"#;
+        let path = SvgPath::from_svg(svg).unwrap();
+        assert_eq!(ends(&path), [Vec2::new(1.0, 2.0), Vec2::new(3.0, 4.0)]);
+
+        assert!(matches!(
+            SvgPath::from_svg("<svg></svg>"),
+            Err(SvgPathError::MissingPath)
+        ));
+    }
+}
@@ -0,0 +1,78 @@
+/* SPDX-License-Identifier: MIT
+* Copyright (c) 2024 Louis Mayencourt
+*/
+
+//! Headless aquarium running the simulation of the creatures, for the tests.
+
+use std::time::Duration;
+
+use bevy::{prelude::*, time::TimeUpdateStrategy};
+
+use crate::creatures::body_parts::{eye::blink, leg::draw_leg, tentacle::move_tentacles};
+use crate::creatures::interpolation::{
+    add_interpolation, interpolate_chains, store_current, store_previous,
+};
+use crate::creatures::kinematic_chain::reach_target;
+use crate::creatures::species::definition::{build_species, Species, SpeciesDefinition};
+use crate::creatures::{Creature, SimulationSet};
+use crate::simulation_rng::{seed_entity_rngs, SimulationRng};
+use crate::steering::{steer, Behaviour, Steering};
+
+/// Length of a simulation step, a whole number of milliseconds
+pub const STEP: Duration = Duration::from_millis(16);
+
+/// Simulation steps run so far
+#[derive(Resource, Default)]
+pub struct Steps(pub usize);
+
+/// Aquarium without window nor rendering, seeded with `seed` and updated
+/// every `frame`
+pub fn aquarium(seed: u64, frame: Duration) -> App {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .insert_resource(TimeUpdateStrategy::ManualDuration(frame))
+        .insert_resource(Time::<Fixed>::from_duration(STEP))
+        .insert_resource(SimulationRng::new(seed))
+        .init_resource::<Steps>()
+        .init_resource::<Assets<SpeciesDefinition>>()
+        .init_resource::<Assets<Mesh>>()
+        .init_resource::<Assets<ColorMaterial>>()
+        .add_event::<AssetEvent<SpeciesDefinition>>()
+        .configure_sets(
+            FixedUpdate,
+            (SimulationSet::Target, SimulationSet::Animation, SimulationSet::Solve).chain(),
+        )
+        .add_systems(PreUpdate, seed_entity_rngs)
+        .add_systems(Update, (build_species, interpolate_chains).chain())
+        .add_systems(FixedFirst, (add_interpolation, store_previous).chain())
+        .add_systems(FixedUpdate, steer.in_set(SimulationSet::Target))
+        .add_systems(
+            FixedUpdate,
+            (draw_leg, move_tentacles, blink).in_set(SimulationSet::Animation),
+        )
+        .add_systems(FixedUpdate, reach_target.in_set(SimulationSet::Solve))
+        .add_systems(FixedUpdate, |mut steps: ResMut<Steps>| steps.0 += 1)
+        .add_systems(FixedLast, store_current);
+    app
+}
+
+/// Spawn a creature of the species described by `file`, wandering around
+pub fn spawn_wanderer(app: &mut App, file: &str) -> Entity {
+    let definition = SpeciesDefinition::from_bytes(file.as_bytes()).unwrap();
+    let species = app.world_mut().resource_mut::<Assets<SpeciesDefinition>>().add(definition);
+    let wander = Behaviour::Wander { distance: 80.0, radius: 40.0, jitter: 360.0 };
+    app.world_mut()
+        .spawn((
+            Creature,
+            Species(species),
+            Steering::new(120.0, 300.0, 180.0).with(wander, 1.0),
+        ))
+        .id()
+}
+
+/// Update the aquarium until it ran `steps` simulation steps
+pub fn run_steps(app: &mut App, steps: usize) {
+    while app.world().resource::<Steps>().0 < steps {
+        app.update();
+    }
+}
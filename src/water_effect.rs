@@ -51,7 +51,7 @@ fn setup(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut water_material: ResMut<Assets<WaterMaterial>>,
-    mut water_floor_material: ResMut<Assets<WaterFloorMaterial>>,
+    _water_floor_material: ResMut<Assets<WaterFloorMaterial>>,
     mut images: ResMut<Assets<Image>>,
     windows: Query<&Window>,
 ) {